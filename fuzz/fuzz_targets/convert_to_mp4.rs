@@ -0,0 +1,25 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use slidevid::{convert_to_mp4, Frame};
+use std::{env, fs, panic, process};
+
+// The first byte picks the frames, the rest is the archive. Malformed input must come back as
+// an error; a panic aborts, which libFuzzer reports as a crash.
+fuzz_target!(|data: &[u8]| {
+    let (selector, zip) = match data.split_first() {
+        Some((&selector, zip)) => (selector, zip),
+        None => return,
+    };
+    let frames = match selector % 3 {
+        0 => Vec::new(),
+        1 => vec![Frame::new("0.png", 100)],
+        _ => vec![Frame::new("0.jpg", u32::from(selector))],
+    };
+    let output = env::temp_dir().join(format!("slidevid-fuzz-{}.mp4", process::id()));
+    let result = panic::catch_unwind(|| convert_to_mp4(zip, &frames, &output));
+    let _ = fs::remove_file(&output);
+    if result.is_err() {
+        process::abort();
+    }
+});
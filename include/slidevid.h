@@ -0,0 +1,58 @@
+/* The C interface of slidevid, built with the `ffi` feature. See src/ffi.rs for the details. */
+
+#ifndef SLIDEVID_H
+#define SLIDEVID_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define SLIDEVID_OK 0
+#define SLIDEVID_ERROR 1
+#define SLIDEVID_CANCELLED 2
+#define SLIDEVID_ENCRYPTED_ARCHIVE 3
+#define SLIDEVID_EMPTY_ARCHIVE 4
+#define SLIDEVID_PANIC 5
+#define SLIDEVID_FRAME_TOO_LARGE 6
+#define SLIDEVID_WRONG_PASSWORD 7
+#define SLIDEVID_MISSING_FRAME 8
+#define SLIDEVID_CODEC_NOT_FOUND 9
+#define SLIDEVID_INVALID_DELAY 10
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct SlidevidFrame {
+    /* The name of the frame's entry in the archive, as NUL-terminated UTF-8. */
+    const char *filename;
+    uint32_t delay_ms;
+} SlidevidFrame;
+
+/*
+ * Converts the `zip_len` bytes of a zip archive at `zip` into a video at `output_path`, returning
+ * SLIDEVID_OK or one of the other SLIDEVID_* codes, with the details available from
+ * slidevid_last_error_message().
+ */
+int slidevid_convert(const uint8_t *zip, size_t zip_len, const SlidevidFrame *frames,
+                     size_t frame_count, const char *output_path);
+
+/*
+ * The message of the last error on this thread, or NULL if the last call succeeded. It stays
+ * valid until the next call into slidevid on the same thread.
+ */
+const char *slidevid_last_error_message(void);
+
+/*
+ * Only in builds with the `wasm` feature: converts like slidevid_convert, but into MP4 bytes
+ * stored at `*output`, `*output_len` long, which the caller releases with slidevid_free().
+ */
+int slidevid_convert_to_mp4(const uint8_t *zip, size_t zip_len, const SlidevidFrame *frames,
+                            size_t frame_count, uint8_t **output, size_t *output_len);
+
+void slidevid_free(uint8_t *data, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SLIDEVID_H */
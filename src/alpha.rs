@@ -0,0 +1,119 @@
+use anyhow::Result;
+use ffmpeg::{format::Pixel, frame, software::scaling};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rgb(pub [u8; 3]);
+
+impl Default for Rgb {
+    fn default() -> Self {
+        Rgb([0xff; 3])
+    }
+}
+
+/// Flattens frames with an alpha channel onto a solid background, since none of the output
+/// pixel formats can carry alpha and the scaler would otherwise drop it, leaving black.
+pub(crate) struct Compositor {
+    background: Rgb,
+    converter: Option<scaling::Context>,
+    rgba: frame::Video,
+    composited: frame::Video,
+}
+
+impl Compositor {
+    pub(crate) fn new(background: Rgb) -> Self {
+        Self {
+            background,
+            converter: None,
+            rgba: frame::Video::empty(),
+            composited: frame::Video::empty(),
+        }
+    }
+
+    pub(crate) fn apply<'a>(&'a mut self, source: &'a frame::Video) -> Result<&'a frame::Video> {
+        if !has_alpha(source) {
+            return Ok(source);
+        }
+        let (format, width, height) = (source.format(), source.width(), source.height());
+        match &mut self.converter {
+            Some(converter) => {
+                let input = converter.input();
+                if input.format != format || input.width != width || input.height != height {
+                    converter.cached(
+                        format,
+                        width,
+                        height,
+                        Pixel::RGBA,
+                        width,
+                        height,
+                        scaling::Flags::POINT,
+                    );
+                    self.rgba = frame::Video::empty();
+                    self.composited = frame::Video::new(Pixel::RGB24, width, height);
+                }
+            }
+            converter => {
+                *converter = Some(scaling::Context::get(
+                    format,
+                    width,
+                    height,
+                    Pixel::RGBA,
+                    width,
+                    height,
+                    scaling::Flags::POINT,
+                )?);
+                self.composited = frame::Video::new(Pixel::RGB24, width, height);
+            }
+        }
+        self.converter
+            .as_mut()
+            .unwrap()
+            .run(source, &mut self.rgba)?;
+        self.blend();
+        let err = unsafe {
+            ffmpeg_sys::av_frame_copy_props(self.composited.as_mut_ptr(), source.as_ptr())
+        };
+        if err < 0 {
+            return Err(ffmpeg::Error::from(err).into());
+        }
+        Ok(&self.composited)
+    }
+
+    fn blend(&mut self) {
+        let Rgb(background) = self.background;
+        let (width, height) = (self.rgba.width() as usize, self.rgba.height() as usize);
+        let (src_stride, dst_stride) = (self.rgba.stride(0), self.composited.stride(0));
+        let src = self.rgba.data(0);
+        let dst = self.composited.data_mut(0);
+        for y in 0..height {
+            let src_row = &src[y * src_stride..][..width * 4];
+            let dst_row = &mut dst[y * dst_stride..][..width * 3];
+            for (src, dst) in src_row.chunks_exact(4).zip(dst_row.chunks_exact_mut(3)) {
+                let alpha = src[3] as u32;
+                for c in 0..3 {
+                    let blended = src[c] as u32 * alpha + background[c] as u32 * (255 - alpha);
+                    dst[c] = ((blended + 127) / 255) as u8;
+                }
+            }
+        }
+    }
+}
+
+// Every pixel format with 2 (gray + alpha) or 4 components carries an alpha channel, while
+// paletted frames keep theirs in the palette.
+fn has_alpha(frame: &frame::Video) -> bool {
+    if frame.format() == Pixel::PAL8 {
+        return palette(frame)
+            .is_some_and(|palette| palette.iter().any(|&argb| argb >> 24 != 0xff));
+    }
+    frame
+        .format()
+        .descriptor()
+        .is_some_and(|descriptor| matches!(descriptor.nb_components(), 2 | 4))
+}
+
+/// The 256 ARGB colors of a paletted frame.
+fn palette(frame: &frame::Video) -> Option<&[u32]> {
+    let data = unsafe { (*frame.as_ptr()).data[1] };
+    (!data.is_null()).then(|| unsafe { std::slice::from_raw_parts(data as *const u32, 256) })
+}
@@ -0,0 +1,46 @@
+use crate::{convert, CancellationToken, ConvertOptions, EncodeStats, Frame};
+use anyhow::Result;
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Reads the zip archive from `zip` without blocking the runtime, then converts it on a blocking
+/// thread. Dropping the returned future, e.g. from a `tokio::select!` branch, cancels the
+/// conversion, through the token in `options` if it has one.
+///
+/// The whole archive is read into memory before the conversion starts, since the zip reader
+/// needs to seek, so `zip` only has to be `AsyncRead` and archives are held in full.
+pub async fn convert_to_mp4_async<R, S, O>(
+    mut zip: R,
+    frames: &[Frame<S>],
+    output_path: O,
+    options: &ConvertOptions,
+) -> Result<EncodeStats>
+where
+    R: AsyncRead + Unpin,
+    S: AsRef<str>,
+    O: AsRef<Path>,
+{
+    let mut data = Vec::new();
+    zip.read_to_end(&mut data).await?;
+    let frames: Vec<_> = frames.iter().map(Frame::to_owned_frame).collect();
+    let output_path = output_path.as_ref().to_owned();
+    let token = options.cancellation_token.clone().unwrap_or_default();
+    let mut options = options.clone();
+    options.cancellation_token = Some(token.clone());
+    let mut guard = CancelOnDrop(Some(token));
+    let joined =
+        tokio::task::spawn_blocking(move || convert(data, &frames, output_path, &options)).await;
+    // Only dropping the future cancels; a failed conversion leaves the caller's token alone.
+    guard.0 = None;
+    joined?
+}
+
+struct CancelOnDrop(Option<CancellationToken>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = &self.0 {
+            token.cancel();
+        }
+    }
+}
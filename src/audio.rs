@@ -0,0 +1,121 @@
+use crate::{error::SlidevidError, wrap_result, ConvertOptions};
+use anyhow::{anyhow, Result};
+use ffmpeg::{
+    codec, encoder,
+    format::{self, sample},
+    frame, ChannelLayout, Packet, Rational, Rescale,
+};
+
+const SAMPLE_RATE: i32 = 48_000;
+const BIT_RATE: usize = 64_000;
+
+/// A silent stereo AAC track muxed alongside the video, for players that won't open video-only
+/// files.
+pub(crate) struct SilentTrack {
+    encoder: encoder::audio::Encoder,
+    frame: frame::Audio,
+    packet: Packet,
+    stream_index: usize,
+    /// Samples encoded so far, which is where the next frame starts.
+    samples: i64,
+}
+
+impl SilentTrack {
+    /// Adds the track's stream to `output`, which must not have had its header written yet.
+    pub(crate) fn new(
+        output: &mut format::context::Output,
+        options: &ConvertOptions,
+    ) -> Result<Self> {
+        if !options.container.holds_aac() {
+            return Err(anyhow!(
+                "The {:?} container can't hold a silent AAC track",
+                options.container
+            ));
+        }
+        let codec =
+            encoder::find(codec::Id::AAC).ok_or(SlidevidError::CodecNotFound(codec::Id::AAC))?;
+        let mut stream = output.add_stream(codec)?;
+        let mut encoder = stream.codec().encoder().audio()?;
+        encoder.set_rate(SAMPLE_RATE);
+        encoder.set_channel_layout(ChannelLayout::STEREO);
+        encoder.set_channels(ChannelLayout::STEREO.channels());
+        encoder.set_format(format::Sample::F32(sample::Type::Planar));
+        encoder.set_bit_rate(BIT_RATE);
+        encoder.set_time_base(Rational(1, SAMPLE_RATE));
+        if options.container.has_global_header() {
+            encoder.set_flags(codec::Flags::GLOBAL_HEADER);
+        }
+        let encoder = encoder.open_as(codec)?;
+        stream.set_parameters(&encoder);
+        stream.set_time_base(Rational(1, SAMPLE_RATE));
+        let mut frame = frame::Audio::new(
+            encoder.format(),
+            encoder.frame_size() as usize,
+            ChannelLayout::STEREO,
+        );
+        frame.set_rate(SAMPLE_RATE as u32);
+        for plane in 0..frame.planes() {
+            frame.data_mut(plane).fill(0);
+        }
+        Ok(Self {
+            encoder,
+            frame,
+            packet: Packet::empty(),
+            stream_index: stream.index(),
+            samples: 0,
+        })
+    }
+
+    /// Encodes silence until at least `until`, in `time_base`, in whole frames.
+    pub(crate) fn fill(
+        &mut self,
+        output: &mut format::context::Output,
+        until: i64,
+        time_base: Rational,
+    ) -> Result<()> {
+        let until = until.rescale(time_base, Rational(1, SAMPLE_RATE));
+        while self.samples < until {
+            self.send(output)?;
+        }
+        Ok(())
+    }
+
+    /// Encodes silence until exactly `end`, in `time_base`, and flushes the encoder.
+    pub(crate) fn finish(
+        mut self,
+        output: &mut format::context::Output,
+        end: i64,
+        time_base: Rational,
+    ) -> Result<()> {
+        let end = end.rescale(time_base, Rational(1, SAMPLE_RATE));
+        let frame_size = self.frame.samples() as i64;
+        while self.samples + frame_size <= end {
+            self.send(output)?;
+        }
+        // Only the last frame may be shorter than the encoder's frame size.
+        if self.samples < end {
+            self.frame.set_samples((end - self.samples) as usize);
+            self.send(output)?;
+        }
+        self.encoder.send_eof()?;
+        self.receive_packets(output)
+    }
+
+    fn send(&mut self, output: &mut format::context::Output) -> Result<()> {
+        self.frame.set_pts(Some(self.samples));
+        self.encoder.send_frame(&self.frame)?;
+        self.samples += self.frame.samples() as i64;
+        self.receive_packets(output)
+    }
+
+    fn receive_packets(&mut self, output: &mut format::context::Output) -> Result<()> {
+        // Muxers that only support particular time bases replace the requested one.
+        let time_base = output.stream(self.stream_index).unwrap().time_base();
+        while wrap_result(self.encoder.receive_packet(&mut self.packet))? {
+            self.packet.set_stream(self.stream_index);
+            self.packet.rescale_ts(Rational(1, SAMPLE_RATE), time_base);
+            self.packet.write_interleaved(output)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{convert, ConvertOptions, EncodeStats, Frame};
+use anyhow::{anyhow, Result};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// One conversion of [`convert_batch`].
+#[derive(Clone, Debug)]
+pub struct Job {
+    pub zip: Vec<u8>,
+    pub frames: Vec<Frame<String>>,
+    pub output_path: PathBuf,
+    pub options: ConvertOptions,
+}
+
+impl Job {
+    pub fn new(zip: Vec<u8>, frames: Vec<Frame<String>>, output_path: impl Into<PathBuf>) -> Self {
+        Self {
+            zip,
+            frames,
+            output_path: output_path.into(),
+            options: ConvertOptions::default(),
+        }
+    }
+
+    pub fn options(mut self, options: ConvertOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+/// Converts each of `jobs` on up to `concurrency` threads, returning their results in the order
+/// given. A job that fails, or panics, doesn't stop the others.
+pub fn convert_batch(jobs: Vec<Job>, concurrency: usize) -> Vec<Result<EncodeStats>> {
+    let next = AtomicUsize::new(0);
+    let results: Vec<_> = jobs.iter().map(|_| Mutex::new(None)).collect();
+    thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, jobs.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let job = match jobs.get(index) {
+                    Some(job) => job,
+                    None => break,
+                };
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    convert(&job.zip, &job.frames, &job.output_path, &job.options)
+                }))
+                .unwrap_or_else(|_| Err(anyhow!("Converting to {:?} panicked", job.output_path)));
+                *results[index].lock().unwrap() = Some(result);
+            });
+        }
+    });
+    results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap().unwrap())
+        .collect()
+}
@@ -0,0 +1,83 @@
+use crate::{convert_from, output, timing, ConvertOptions, Frame, WriteMode, ZipSource, MILLIS};
+use anyhow::Result;
+use ffmpeg::{format, Rational};
+use std::{
+    borrow::Cow,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+pub(crate) fn add_chapters<S: AsRef<str>>(
+    output: &mut format::context::Output,
+    frames: &[Frame<S>],
+    delays: &[u32],
+    every_slide: bool,
+) -> Result<()> {
+    let end = timing::slide_spans(delays).last().map_or(0, |(_, end)| end);
+    let starts: Vec<_> = frames
+        .iter()
+        .zip(timing::slide_spans(delays))
+        .enumerate()
+        .filter_map(|(index, (frame, (start, _)))| match &frame.chapter_title {
+            Some(title) => Some((start, Cow::Borrowed(title.as_str()))),
+            None if every_slide => Some((start, Cow::Owned(format!("Slide {}", index + 1)))),
+            None => None,
+        })
+        .collect();
+    for (id, (start, title)) in starts.iter().enumerate() {
+        let next = starts.get(id + 1).map_or(end, |&(next, _)| next);
+        output.add_chapter(id as i32, Rational(1, MILLIS), *start, next, title)?;
+    }
+    Ok(())
+}
+
+/// Encodes each chapter, starting at every frame with a chapter title, as its own video in
+/// `output_dir`, returning the paths in chapter order. Frames before the first title form a
+/// chapter of their own. The frame range applies to the whole deck, and no report is written.
+pub fn split_video<Z: AsRef<[u8]>, S: AsRef<str>>(
+    zip: Z,
+    frames: &[Frame<S>],
+    output_dir: &Path,
+    options: &ConvertOptions,
+) -> Result<Vec<PathBuf>> {
+    let source = &mut ZipSource::with_options(Cursor::new(zip.as_ref()), options)?;
+    let mut chapters = Vec::new();
+    let mut rest = timing::select_frames(frames, options)?;
+    while !rest.is_empty() {
+        let len = rest[1..]
+            .iter()
+            .position(|frame| frame.chapter_title.is_some())
+            .map_or(rest.len(), |index| index + 1);
+        let (chapter, next) = rest.split_at(len);
+        chapters.push(chapter);
+        rest = next;
+    }
+    fs::create_dir_all(output_dir)?;
+    // Claiming every path before encoding keeps the chapters' timestamped names apart, and
+    // leaves nothing behind when a chapter fails.
+    let extension = options.container.extensions()[0];
+    let mut paths = (1..=chapters.len())
+        .map(|number| {
+            let path = output_dir.join(format!("chapter_{:02}.{}", number, extension));
+            output::resolve_output_path(&path, options.write_mode)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let chapter_options = ConvertOptions {
+        frame_range: None,
+        write_mode: WriteMode::Overwrite,
+        #[cfg(feature = "report")]
+        report_path: None,
+        ..options.clone()
+    };
+    for (chapter, path) in chapters.into_iter().zip(&mut paths) {
+        convert_from(source, chapter, path.path(), &chapter_options)?;
+    }
+    Ok(paths
+        .iter_mut()
+        .map(|path| {
+            path.keep();
+            path.path().to_owned()
+        })
+        .collect())
+}
@@ -0,0 +1,238 @@
+use crate::ConvertOptions;
+use ffmpeg::{color, encoder, frame, software::scaling};
+use std::{borrow::Cow, os::raw::c_int};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorSpace {
+    #[default]
+    Bt709,
+    Bt601,
+    /// BT.2020 non-constant luminance, for wide gamut and HDR output.
+    Bt2020Ncl,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorRange {
+    /// Limited (16-235) range, also known as MPEG range.
+    Tv,
+    /// Full (0-255) range, also known as PC or JPEG range.
+    #[default]
+    Full,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorPrimaries {
+    #[default]
+    Bt709,
+    Bt470bg,
+    Smpte170m,
+    Bt2020,
+}
+
+/// Transfer characteristic the output is tagged with, for players to map it to the display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorTrc {
+    Bt709,
+    Smpte170m,
+    /// sRGB.
+    Iec61966_2_1,
+    /// HDR10's perceptual quantizer.
+    Smpte2084,
+    /// Hybrid log-gamma.
+    AribStdB67,
+}
+
+impl ColorSpace {
+    fn from_space(space: color::Space) -> Option<Self> {
+        match space {
+            color::Space::BT709 => Some(ColorSpace::Bt709),
+            color::Space::BT470BG | color::Space::SMPTE170M => Some(ColorSpace::Bt601),
+            color::Space::BT2020NCL => Some(ColorSpace::Bt2020Ncl),
+            _ => None,
+        }
+    }
+
+    fn space(self) -> color::Space {
+        match self {
+            ColorSpace::Bt709 => color::Space::BT709,
+            ColorSpace::Bt601 => color::Space::SMPTE170M,
+            ColorSpace::Bt2020Ncl => color::Space::BT2020NCL,
+        }
+    }
+
+    fn transfer(self) -> color::TransferCharacteristic {
+        match self {
+            ColorSpace::Bt709 => color::TransferCharacteristic::BT709,
+            ColorSpace::Bt601 => color::TransferCharacteristic::SMPTE170M,
+            ColorSpace::Bt2020Ncl => color::TransferCharacteristic::BT2020_10,
+        }
+    }
+
+    fn coefficients(self) -> c_int {
+        match self {
+            ColorSpace::Bt709 => ffmpeg_sys::SWS_CS_ITU709 as c_int,
+            ColorSpace::Bt601 => ffmpeg_sys::SWS_CS_ITU601 as c_int,
+            ColorSpace::Bt2020Ncl => ffmpeg_sys::SWS_CS_BT2020 as c_int,
+        }
+    }
+}
+
+impl ColorRange {
+    fn from_range(range: color::Range) -> Option<Self> {
+        match range {
+            color::Range::MPEG => Some(ColorRange::Tv),
+            color::Range::JPEG => Some(ColorRange::Full),
+            _ => None,
+        }
+    }
+
+    fn range(self) -> color::Range {
+        match self {
+            ColorRange::Tv => color::Range::MPEG,
+            ColorRange::Full => color::Range::JPEG,
+        }
+    }
+}
+
+impl ColorPrimaries {
+    fn from_primaries(primaries: color::Primaries) -> Option<Self> {
+        match primaries {
+            color::Primaries::BT709 => Some(ColorPrimaries::Bt709),
+            color::Primaries::BT470BG => Some(ColorPrimaries::Bt470bg),
+            color::Primaries::SMPTE170M => Some(ColorPrimaries::Smpte170m),
+            color::Primaries::BT2020 => Some(ColorPrimaries::Bt2020),
+            _ => None,
+        }
+    }
+
+    fn primaries(self) -> color::Primaries {
+        match self {
+            ColorPrimaries::Bt709 => color::Primaries::BT709,
+            ColorPrimaries::Bt470bg => color::Primaries::BT470BG,
+            ColorPrimaries::Smpte170m => color::Primaries::SMPTE170M,
+            ColorPrimaries::Bt2020 => color::Primaries::BT2020,
+        }
+    }
+}
+
+impl ColorTrc {
+    fn from_transfer(transfer: color::TransferCharacteristic) -> Option<Self> {
+        match transfer {
+            color::TransferCharacteristic::BT709 => Some(ColorTrc::Bt709),
+            color::TransferCharacteristic::SMPTE170M => Some(ColorTrc::Smpte170m),
+            color::TransferCharacteristic::IEC61966_2_1 => Some(ColorTrc::Iec61966_2_1),
+            color::TransferCharacteristic::SMPTE2084 => Some(ColorTrc::Smpte2084),
+            color::TransferCharacteristic::ARIB_STD_B67 => Some(ColorTrc::AribStdB67),
+            _ => None,
+        }
+    }
+
+    fn transfer(self) -> color::TransferCharacteristic {
+        match self {
+            ColorTrc::Bt709 => color::TransferCharacteristic::BT709,
+            ColorTrc::Smpte170m => color::TransferCharacteristic::SMPTE170M,
+            ColorTrc::Iec61966_2_1 => color::TransferCharacteristic::IEC61966_2_1,
+            ColorTrc::Smpte2084 => color::TransferCharacteristic::SMPTE2084,
+            ColorTrc::AribStdB67 => color::TransferCharacteristic::ARIB_STD_B67,
+        }
+    }
+}
+
+/// `options` with the colors it leaves unset taken from `first`, the first decoded slide, where
+/// the decoder tagged them. RGB slides have no matrix or range to keep, so the defaults of BT.709
+/// and full range still apply to them.
+pub(crate) fn resolve<'a>(
+    options: &'a ConvertOptions,
+    first: &frame::Video,
+) -> Cow<'a, ConvertOptions> {
+    let resolved = (
+        options
+            .colorspace
+            .or_else(|| ColorSpace::from_space(first.color_space())),
+        options
+            .color_range
+            .or_else(|| ColorRange::from_range(first.color_range())),
+        options
+            .color_primaries
+            .or_else(|| ColorPrimaries::from_primaries(first.color_primaries())),
+        options
+            .color_trc
+            .or_else(|| ColorTrc::from_transfer(first.color_transfer_characteristic())),
+    );
+    let current = (
+        options.colorspace,
+        options.color_range,
+        options.color_primaries,
+        options.color_trc,
+    );
+    if resolved == current {
+        return Cow::Borrowed(options);
+    }
+    let (colorspace, color_range, color_primaries, color_trc) = resolved;
+    Cow::Owned(ConvertOptions {
+        colorspace,
+        color_range,
+        color_primaries,
+        color_trc,
+        ..options.clone()
+    })
+}
+
+fn colorspace(options: &ConvertOptions) -> ColorSpace {
+    options.colorspace.unwrap_or_default()
+}
+
+fn color_range(options: &ConvertOptions) -> ColorRange {
+    options.color_range.unwrap_or_default()
+}
+
+/// The transfer characteristic set with [`ConvertOptions::color_trc`], or else the colorspace's.
+fn transfer(options: &ConvertOptions) -> color::TransferCharacteristic {
+    options
+        .color_trc
+        .map_or_else(|| colorspace(options).transfer(), ColorTrc::transfer)
+}
+
+pub(crate) fn configure_scaler(scaler: &mut scaling::Context, options: &ConvertOptions) {
+    // Decoded slides are RGB or full range JPEG, so only the destination side varies.
+    unsafe {
+        let src = ffmpeg_sys::sws_getCoefficients(ffmpeg_sys::SWS_CS_DEFAULT as c_int);
+        let dst = ffmpeg_sys::sws_getCoefficients(colorspace(options).coefficients());
+        let dst_range = (color_range(options) == ColorRange::Full) as c_int;
+        ffmpeg_sys::sws_setColorspaceDetails(
+            scaler.as_mut_ptr(),
+            src,
+            1,
+            dst,
+            dst_range,
+            0,
+            1 << 16,
+            1 << 16,
+        );
+    }
+}
+
+pub(crate) fn tag_encoder(encoder: &mut encoder::video::Video, options: &ConvertOptions) {
+    encoder.set_colorspace(colorspace(options).space());
+    encoder.set_color_range(color_range(options).range());
+    unsafe {
+        let ptr = encoder.as_mut_ptr();
+        (*ptr).color_primaries = options
+            .color_primaries
+            .unwrap_or_default()
+            .primaries()
+            .into();
+        (*ptr).color_trc = transfer(options).into();
+    }
+}
+
+pub(crate) fn tag_frame(frame: &mut frame::Video, options: &ConvertOptions) {
+    frame.set_color_space(colorspace(options).space());
+    frame.set_color_range(color_range(options).range());
+    frame.set_color_primaries(options.color_primaries.unwrap_or_default().primaries());
+    frame.set_color_transfer_characteristic(transfer(options));
+}
@@ -0,0 +1,146 @@
+use anyhow::{anyhow, Result};
+use ffmpeg::codec;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Container {
+    #[default]
+    Mp4,
+    /// MP4 written as a series of self-contained fragments, playable while it's being written
+    /// and without seeking back to the start.
+    FragmentedMp4,
+    Mov,
+    Mkv,
+    WebM,
+    /// FFmpeg's own container, which holds the uncompressed video other FFmpeg processes read.
+    Nut,
+    /// A bare H.264 elementary stream of Annex B NAL units, without a container.
+    AnnexB,
+    /// An animated GIF, with every slide quantized to its own 256-color palette. It loops
+    /// forever unless `loop` is set through [`ConvertOptions::muxer_options`].
+    ///
+    /// [`ConvertOptions::muxer_options`]: crate::ConvertOptions::muxer_options
+    Gif,
+}
+
+impl Container {
+    /// The container an output path with extension `ext` is written in, if it's a known one.
+    pub fn from_extension(ext: &str) -> Option<Container> {
+        [
+            Container::Mp4,
+            Container::Mov,
+            Container::Mkv,
+            Container::WebM,
+            Container::Nut,
+            Container::AnnexB,
+            Container::Gif,
+        ]
+        .iter()
+        .copied()
+        .find(|container| {
+            container
+                .extensions()
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(ext))
+        })
+    }
+
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Container::Mp4 | Container::FragmentedMp4 => &["mp4", "m4v"],
+            Container::Mov => &["mov"],
+            Container::Mkv => &["mkv"],
+            Container::WebM => &["webm"],
+            Container::Nut => &["nut"],
+            Container::AnnexB => &["h264", "264"],
+            Container::Gif => &["gif"],
+        }
+    }
+
+    pub fn format_name(self) -> &'static str {
+        match self {
+            Container::Mp4 | Container::FragmentedMp4 => "mp4",
+            Container::Mov => "mov",
+            Container::Mkv => "matroska",
+            Container::WebM => "webm",
+            Container::Nut => "nut",
+            Container::AnnexB => "h264",
+            Container::Gif => "gif",
+        }
+    }
+
+    pub fn codec(self) -> codec::Id {
+        match self {
+            Container::Mp4
+            | Container::FragmentedMp4
+            | Container::Mov
+            | Container::Mkv
+            | Container::AnnexB => codec::Id::H264,
+            Container::WebM => codec::Id::VP9,
+            Container::Nut => codec::Id::RAWVIDEO,
+            Container::Gif => codec::Id::GIF,
+        }
+    }
+
+    /// Whether the container keeps the codec's parameter sets in its header, rather than the
+    /// stream repeating them in band.
+    pub(crate) fn has_global_header(self) -> bool {
+        !matches!(self, Container::AnnexB | Container::Gif)
+    }
+
+    /// Whether the container can hold the AAC track of [`ConvertOptions::silent_audio`].
+    ///
+    /// [`ConvertOptions::silent_audio`]: crate::ConvertOptions::silent_audio
+    pub(crate) fn holds_aac(self) -> bool {
+        matches!(
+            self,
+            Container::Mp4 | Container::FragmentedMp4 | Container::Mov | Container::Mkv
+        )
+    }
+
+    /// Whether the container can be written without seeking back into the output.
+    pub fn is_streamable(self) -> bool {
+        !matches!(self, Container::Mp4 | Container::Mov)
+    }
+
+    /// Muxer options the container needs, which [`ConvertOptions::muxer_options`] can override.
+    ///
+    /// [`ConvertOptions::muxer_options`]: crate::ConvertOptions::muxer_options
+    pub(crate) fn muxer_options(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Container::FragmentedMp4 => &[("movflags", "frag_keyframe+empty_moov")],
+            _ => &[],
+        }
+    }
+}
+
+pub fn validate_output_format(path: &Path, container: Container) -> Result<()> {
+    let expected = container.extensions();
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    if expected.iter().any(|e| e.eq_ignore_ascii_case(ext)) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Output path {:?} doesn't match the {:?} container, expected one of: .{}",
+            path,
+            container,
+            expected.join(", .")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_containers_by_extension() {
+        assert_eq!(Container::from_extension("MKV"), Some(Container::Mkv));
+        assert_eq!(Container::from_extension("264"), Some(Container::AnnexB));
+        assert_eq!(Container::from_extension("gif"), Some(Container::Gif));
+        assert_eq!(Container::from_extension("avi"), None);
+        assert!(validate_output_format(Path::new("deck.gif"), Container::Gif).is_ok());
+        assert!(validate_output_format(Path::new("deck.mp4"), Container::Gif).is_err());
+    }
+}
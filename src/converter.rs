@@ -0,0 +1,34 @@
+use crate::{convert, find_encoder, ConvertOptions, EncodeStats, Frame};
+use anyhow::Result;
+use std::path::Path;
+
+/// Converts many decks with the same options.
+///
+/// FFmpeg is initialized and the encoder is looked up and checked against the options once, in
+/// [`Converter::new`]. Decoders, the scaler and the encoder itself are still created per call,
+/// since their state is tied to each deck's dimensions and output.
+#[derive(Clone, Debug)]
+pub struct Converter {
+    options: ConvertOptions,
+}
+
+impl Converter {
+    pub fn new(options: ConvertOptions) -> Result<Self> {
+        ffmpeg::init()?;
+        find_encoder(&options)?;
+        Ok(Self { options })
+    }
+
+    pub fn options(&self) -> &ConvertOptions {
+        &self.options
+    }
+
+    pub fn convert<Z: AsRef<[u8]>, S: AsRef<str>, O: AsRef<Path>>(
+        &self,
+        zip: Z,
+        frames: &[Frame<S>],
+        output_path: O,
+    ) -> Result<EncodeStats> {
+        convert(zip, frames, output_path, &self.options)
+    }
+}
@@ -0,0 +1,45 @@
+use anyhow::{anyhow, Result};
+use ffmpeg::frame;
+use std::os::raw::c_int;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, w: u32, h: u32) -> Self {
+        Self { x, y, w, h }
+    }
+}
+
+/// Crops `frame` in place to `rect`, clamped to the frame bounds, by offsetting its data pointers.
+pub(crate) fn apply(frame: &mut frame::Video, rect: Rect) -> Result<()> {
+    let (width, height) = (frame.width(), frame.height());
+    if rect.w == 0 || rect.h == 0 || rect.x >= width || rect.y >= height {
+        return Err(anyhow!(
+            "Crop rectangle {:?} lies outside the {}x{} frame",
+            rect,
+            width,
+            height
+        ));
+    }
+    let right = rect.x.saturating_add(rect.w).min(width);
+    let bottom = rect.y.saturating_add(rect.h).min(height);
+    let err = unsafe {
+        let ptr = frame.as_mut_ptr();
+        (*ptr).crop_left = rect.x as usize;
+        (*ptr).crop_top = rect.y as usize;
+        (*ptr).crop_right = (width - right) as usize;
+        (*ptr).crop_bottom = (height - bottom) as usize;
+        ffmpeg_sys::av_frame_apply_cropping(ptr, ffmpeg_sys::AV_FRAME_CROP_UNALIGNED as c_int)
+    };
+    if err < 0 {
+        return Err(ffmpeg::Error::from(err).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use crate::{
+    decoder_id, open_decoder_as, receive_frame, send_packet, ConvertOptions, Frame, FrameSource,
+    ZipSource, DECODER_TIME_BASE,
+};
+use anyhow::{anyhow, Result};
+use ffmpeg::{codec, frame};
+use std::{
+    io::{Read, Seek},
+    iter::Enumerate,
+    slice,
+};
+
+pub struct DecodedFrame<'a, S: AsRef<str>> {
+    pub index: usize,
+    pub frame: &'a Frame<S>,
+    /// The decoded image, timestamped in milliseconds from the start of the deck.
+    pub video: frame::Video,
+}
+
+/// Decodes the slides of a zip archive one at a time, as the encoder would see them.
+pub struct FrameIterator<'a, R, S: AsRef<str>> {
+    source: ZipSource<R>,
+    frames: Enumerate<slice::Iter<'a, Frame<S>>>,
+    timestamp: i64,
+    force_input_codec: Option<codec::Id>,
+    default_delay_ms: Option<u32>,
+}
+
+/// Decodes `frames` from `zip`. Frames without a delay last no time at all, the next one
+/// starting at the same timestamp.
+pub fn decode_frames<R: Read + Seek, S: AsRef<str>>(
+    zip: R,
+    frames: &[Frame<S>],
+) -> Result<FrameIterator<'_, R, S>> {
+    Ok(FrameIterator {
+        source: ZipSource::new(zip)?,
+        frames: frames.iter().enumerate(),
+        timestamp: 0,
+        force_input_codec: None,
+        default_delay_ms: None,
+    })
+}
+
+/// Like [`decode_frames`], reading and decoding the slides as a conversion with `options`
+/// would, and timing frames without a delay by [`ConvertOptions::default_delay_ms`].
+pub fn decode_frames_with<'a, R: Read + Seek, S: AsRef<str>>(
+    zip: R,
+    frames: &'a [Frame<S>],
+    options: &ConvertOptions,
+) -> Result<FrameIterator<'a, R, S>> {
+    Ok(FrameIterator {
+        source: ZipSource::with_options(zip, options)?,
+        frames: frames.iter().enumerate(),
+        timestamp: 0,
+        force_input_codec: options.force_input_codec,
+        default_delay_ms: options.default_delay_ms,
+    })
+}
+
+impl<'a, R: Read + Seek, S: AsRef<str>> FrameIterator<'a, R, S> {
+    fn decode(&mut self, frame: &Frame<S>) -> Result<frame::Video> {
+        let filename = frame.filename.as_ref();
+        let data = self.source.read_frame(filename)?;
+        let id = self
+            .force_input_codec
+            .unwrap_or_else(|| decoder_id(filename));
+        let mut decoder = open_decoder_as(id)?;
+        send_packet(
+            &mut decoder,
+            &data,
+            &mut self.timestamp,
+            frame.delay.or(self.default_delay_ms).unwrap_or(0),
+            DECODER_TIME_BASE,
+            DECODER_TIME_BASE,
+        )?;
+        decoder.send_eof()?;
+        let mut video = frame::Video::empty();
+        receive_frame(&mut decoder, &mut video)?
+            .ok_or_else(|| anyhow!("{:?} didn't decode to a frame", filename))?;
+        Ok(video)
+    }
+}
+
+impl<'a, R: Read + Seek, S: AsRef<str>> Iterator for FrameIterator<'a, R, S> {
+    type Item = Result<DecodedFrame<'a, S>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, frame) = self.frames.next()?;
+        Some(self.decode(frame).map(|video| DecodedFrame {
+            index,
+            frame,
+            video,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::make_test_zip;
+    use std::io::Cursor;
+
+    #[test]
+    fn decodes_frames_without_a_delay() {
+        let zip = make_test_zip(&[(32, 32, "png"), (32, 32, "png")]);
+        let frames = [Frame::new("0.png", 300), Frame::without_delay("1.png")];
+        let options = ConvertOptions::new().default_delay_ms(700);
+        let decoded = decode_frames_with(Cursor::new(&zip), &frames, &options)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let starts: Vec<_> = decoded.iter().map(|frame| frame.video.pts()).collect();
+        assert_eq!(starts, vec![Some(0), Some(300)]);
+        assert_eq!(
+            decode_frames(Cursor::new(&zip), &frames).unwrap().count(),
+            2
+        );
+    }
+}
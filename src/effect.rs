@@ -0,0 +1,86 @@
+use crate::PixelFormat;
+use ffmpeg::frame;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorFilter {
+    Grayscale,
+    Sepia,
+}
+
+impl ColorFilter {
+    // Chroma (Cb, Cr) offsets from neutral at 8 bits; luma is kept as is.
+    fn chroma_offsets(self) -> (i32, i32) {
+        match self {
+            ColorFilter::Grayscale => (0, 0),
+            ColorFilter::Sepia => (-14, 16),
+        }
+    }
+}
+
+pub(crate) fn apply(frame: &mut frame::Video, filter: ColorFilter, format: PixelFormat) {
+    let depth = format.bit_depth().bits();
+    let neutral = 1 << (depth - 1);
+    let (cb, cr) = filter.chroma_offsets();
+    fill(frame.data_mut(1), neutral + (cb << (depth - 8)), depth);
+    fill(frame.data_mut(2), neutral + (cr << (depth - 8)), depth);
+}
+
+fn fill(plane: &mut [u8], value: i32, depth: u32) {
+    if depth == 8 {
+        plane.fill(value as u8);
+    } else {
+        let value = (value as u16).to_le_bytes();
+        for sample in plane.chunks_exact_mut(2) {
+            sample.copy_from_slice(&value);
+        }
+    }
+}
+
+/// Unsharp-masks the luma plane, adding `strength` times the difference between each sample and
+/// the average of the 3x3 block around it. `luma` is scratch space, kept between calls so it's
+/// only allocated once.
+pub(crate) fn sharpen(
+    frame: &mut frame::Video,
+    luma: &mut Vec<f32>,
+    strength: f32,
+    format: PixelFormat,
+) {
+    let depth = format.bit_depth().bits();
+    let bytes = if depth == 8 { 1 } else { 2 };
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    let stride = frame.stride(0);
+    let plane = frame.data_mut(0);
+    luma.clear();
+    for row in plane.chunks(stride).take(height) {
+        luma.extend(
+            row[..width * bytes]
+                .chunks_exact(bytes)
+                .map(|sample| match sample {
+                    &[value] => value as f32,
+                    _ => u16::from_le_bytes([sample[0], sample[1]]) as f32,
+                }),
+        );
+    }
+    let max = ((1 << depth) - 1) as f32;
+    for y in 0..height {
+        let rows = y.saturating_sub(1)..(y + 2).min(height);
+        for x in 0..width {
+            let columns = x.saturating_sub(1)..(x + 2).min(width);
+            let block = rows
+                .clone()
+                .flat_map(|ny| &luma[ny * width..][columns.clone()]);
+            let blurred = block.sum::<f32>() / (rows.len() * columns.len()) as f32;
+            let value = luma[y * width + x];
+            let sharpened = (value + strength * (value - blurred))
+                .round()
+                .clamp(0.0, max);
+            let i = y * stride + x * bytes;
+            if bytes == 1 {
+                plane[i] = sharpened as u8;
+            } else {
+                plane[i..i + 2].copy_from_slice(&(sharpened as u16).to_le_bytes());
+            }
+        }
+    }
+}
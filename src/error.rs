@@ -0,0 +1,90 @@
+use ffmpeg::codec;
+use std::io;
+use thiserror::Error;
+
+/// The failures callers may want to tell apart, downcast from the [`anyhow::Error`] most of the
+/// crate's functions return. There, FFmpeg and I/O failures downcast to [`ffmpeg::Error`] and
+/// [`io::Error`] instead; [`convert_to_mp4`](crate::convert_to_mp4) returns this type directly,
+/// with those failures in their own variants.
+#[derive(Debug, Error)]
+pub enum SlidevidError {
+    #[error("Encoding was cancelled")]
+    Cancelled,
+    #[error("{0:?} is encrypted, set the archive's password to convert it")]
+    EncryptedArchive(String),
+    #[error("Wrong password for {0:?}")]
+    WrongPassword(String),
+    #[error("The archive doesn't contain any files")]
+    EmptyArchive,
+    #[error("{0:?} is {1} bytes, over the limit of {2} bytes")]
+    FrameTooLarge(String, u64, u64),
+    /// `available` lists what the source does contain, where it looked beyond the exact name.
+    #[error("No frame named {name:?} in the source{}", list_available(.available))]
+    MissingFrame {
+        name: String,
+        available: Vec<String>,
+    },
+    #[error("Couldn't find a {0:?} codec in this FFmpeg build")]
+    CodecNotFound(codec::Id),
+    #[error("Frame #{frame} has a delay of {delay}ms")]
+    InvalidDelay { frame: usize, delay: u32 },
+    #[error(transparent)]
+    EncoderError(#[from] ffmpeg::Error),
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+    /// Any other failure, such as invalid options or a corrupt archive.
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl SlidevidError {
+    pub(crate) fn missing_frame(name: impl Into<String>) -> Self {
+        Self::MissingFrame {
+            name: name.into(),
+            available: Vec::new(),
+        }
+    }
+}
+
+fn list_available(available: &[String]) -> String {
+    if available.is_empty() {
+        String::new()
+    } else {
+        format!(", which contains: {}", available.join(", "))
+    }
+}
+
+impl From<anyhow::Error> for SlidevidError {
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<SlidevidError>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+        let err = match err.downcast::<ffmpeg::Error>() {
+            Ok(err) => return SlidevidError::EncoderError(err),
+            Err(err) => err,
+        };
+        match err.downcast::<io::Error>() {
+            Ok(err) => SlidevidError::IoError(err),
+            Err(err) => SlidevidError::Other(err),
+        }
+    }
+}
+
+/// Context on a [`SlidevidError::Cancelled`] that came before the first slide was encoded, so the
+/// output never got a header, let alone a trailer.
+#[derive(Debug, Error)]
+#[error("Encoding was cancelled before the first slide")]
+pub(crate) struct NothingWritten;
+
+/// Whether `result` left a finished output behind, which a cancellation after the first slide
+/// still does, truncated at the last slide encoded.
+pub(crate) fn wrote_output<T>(result: &anyhow::Result<T>) -> bool {
+    match result {
+        Ok(_) => true,
+        Err(err) => {
+            matches!(err.downcast_ref(), Some(SlidevidError::Cancelled))
+                && err.downcast_ref::<NothingWritten>().is_none()
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{timing, wrap_result, Frame, LANCZOS, MILLIS};
+use anyhow::{anyhow, Result};
+use ffmpeg::{
+    codec, encoder, format::Pixel, frame, media, software::scaling, Packet, Rational, Rescale,
+};
+use std::{fs::File, io::Write, path::Path};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+/// Image format of the slides [`video_to_zip`] writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+}
+
+impl ImageFormat {
+    fn codec(self) -> codec::Id {
+        match self {
+            ImageFormat::Png => codec::Id::PNG,
+            ImageFormat::Jpeg => codec::Id::MJPEG,
+        }
+    }
+
+    fn pixel(self) -> Pixel {
+        match self {
+            ImageFormat::Png => Pixel::RGB24,
+            ImageFormat::Jpeg => Pixel::YUVJ420P,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+/// Decodes every frame of the video at `video_path` into an image in a new zip archive at
+/// `output_zip`, returning the frames to convert it back with. Frames are scaled to `width` and
+/// `height`, with a missing one following the video's aspect ratio.
+pub fn video_to_zip(
+    video_path: &Path,
+    output_zip: &Path,
+    image_format: ImageFormat,
+    width: Option<u32>,
+    height: Option<u32>,
+) -> Result<Vec<Frame<String>>> {
+    let mut input = ffmpeg::format::input(&video_path)?;
+    let stream = input
+        .streams()
+        .best(media::Type::Video)
+        .ok_or_else(|| anyhow!("{:?} doesn't contain a video stream", video_path))?;
+    let (stream_index, time_base) = (stream.index(), stream.time_base());
+    let end = (stream.duration() > 0).then(|| stream.start_time().max(0) + stream.duration());
+    let mut decoder = codec::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .video()?;
+    let writer = &mut ImageWriter {
+        zip: ZipWriter::new(File::create(output_zip)?),
+        image_format,
+        size: (width, height),
+        encoder: None,
+        packet: Packet::empty(),
+        names: Vec::new(),
+    };
+    let decoded = &mut frame::Video::empty();
+    let mut timestamps = Vec::new();
+    for (stream, packet) in input.packets() {
+        if stream.index() == stream_index {
+            decoder.send_packet(&packet)?;
+            while wrap_result(decoder.receive_frame(decoded))? {
+                timestamps.push(decoded.timestamp().or_else(|| decoded.pts()));
+                writer.write(decoded)?;
+            }
+        }
+    }
+    decoder.send_eof()?;
+    while wrap_result(decoder.receive_frame(decoded))? {
+        timestamps.push(decoded.timestamp().or_else(|| decoded.pts()));
+        writer.write(decoded)?;
+    }
+    writer.zip.finish()?;
+    let delays = delays(&timestamps, end, time_base);
+    Ok(writer
+        .names
+        .drain(..)
+        .zip(delays)
+        .map(|(name, delay)| Frame::new(name, delay))
+        .collect())
+}
+
+struct ImageWriter {
+    zip: ZipWriter<File>,
+    image_format: ImageFormat,
+    size: (Option<u32>, Option<u32>),
+    encoder: Option<(encoder::video::Encoder, scaling::Context, frame::Video)>,
+    packet: Packet,
+    names: Vec<String>,
+}
+
+impl ImageWriter {
+    fn write(&mut self, decoded: &frame::Video) -> Result<()> {
+        let (src_format, src_w, src_h) = (decoded.format(), decoded.width(), decoded.height());
+        if self.encoder.is_none() {
+            let (width, height) = match self.size {
+                (Some(width), Some(height)) => (width, height),
+                (Some(width), None) => (width, scale(src_h, width, src_w)),
+                (None, Some(height)) => (scale(src_w, height, src_h), height),
+                (None, None) => (src_w, src_h),
+            };
+            let format = self.image_format.pixel();
+            let codec = encoder::find(self.image_format.codec())
+                .ok_or_else(|| anyhow!("Couldn't find a {:?} encoder", self.image_format))?;
+            let mut encoder = codec::Context::new().encoder().video()?;
+            encoder.set_width(width);
+            encoder.set_height(height);
+            encoder.set_format(format);
+            encoder.set_time_base(Rational(1, MILLIS));
+            let scaler =
+                scaling::Context::get(src_format, src_w, src_h, format, width, height, LANCZOS)?;
+            let scaled = frame::Video::new(format, width, height);
+            self.encoder = Some((encoder.open_as(codec)?, scaler, scaled));
+        }
+        let (encoder, scaler, scaled) = self.encoder.as_mut().unwrap();
+        let src = scaler.input();
+        if src_format != src.format || src_w != src.width || src_h != src.height {
+            let dst = *scaler.output();
+            scaler.cached(
+                src_format, src_w, src_h, dst.format, dst.width, dst.height, LANCZOS,
+            );
+        }
+        scaler.run(decoded, scaled)?;
+        scaled.set_pts(Some(self.names.len() as i64));
+        encoder.send_frame(scaled)?;
+        while wrap_result(encoder.receive_packet(&mut self.packet))? {
+            let name = format!("{:06}.{}", self.names.len(), self.image_format.extension());
+            let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+            self.zip.start_file(name.as_str(), options)?;
+            self.zip.write_all(self.packet.data().unwrap_or_default())?;
+            self.names.push(name);
+        }
+        Ok(())
+    }
+}
+
+fn scale(value: u32, to: u32, from: u32) -> u32 {
+    (value as u64 * to as u64 / from.max(1) as u64).max(1) as u32
+}
+
+// Each frame lasts until the next one starts. The last one lasts until the end of the stream, or
+// failing that as long as the one before it.
+fn delays(timestamps: &[Option<i64>], end: Option<i64>, time_base: Rational) -> Vec<u32> {
+    let mut last = 0;
+    let millis: Vec<i64> = timestamps
+        .iter()
+        .map(|ts| {
+            last = ts.map_or(last, |ts| ts.rescale(time_base, Rational(1, MILLIS)));
+            last
+        })
+        .collect();
+    let end = end.map(|end| end.rescale(time_base, Rational(1, MILLIS)));
+    let mut delays: Vec<u32> = millis
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).max(1) as u32)
+        .collect();
+    if let Some(&start) = millis.last() {
+        let fallback = delays.last().copied().unwrap_or(timing::MIN_DELAY_MS);
+        delays.push(end.map_or(fallback, |end| (end - start).max(1) as u32));
+    }
+    delays
+}
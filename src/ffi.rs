@@ -0,0 +1,227 @@
+//! The C interface, declared for C callers by `include/slidevid.h`.
+
+use crate::{convert, ConvertOptions, Frame, SlidevidError};
+use anyhow::{anyhow, Result};
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+pub const SLIDEVID_OK: c_int = 0;
+pub const SLIDEVID_ERROR: c_int = 1;
+pub const SLIDEVID_CANCELLED: c_int = 2;
+pub const SLIDEVID_ENCRYPTED_ARCHIVE: c_int = 3;
+pub const SLIDEVID_EMPTY_ARCHIVE: c_int = 4;
+pub const SLIDEVID_PANIC: c_int = 5;
+pub const SLIDEVID_FRAME_TOO_LARGE: c_int = 6;
+pub const SLIDEVID_WRONG_PASSWORD: c_int = 7;
+pub const SLIDEVID_MISSING_FRAME: c_int = 8;
+pub const SLIDEVID_CODEC_NOT_FOUND: c_int = 9;
+pub const SLIDEVID_INVALID_DELAY: c_int = 10;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+#[repr(C)]
+pub struct SlidevidFrame {
+    pub filename: *const c_char,
+    pub delay_ms: u32,
+}
+
+/// Converts the `zip_len` bytes of a zip archive at `zip` into a video at `output_path`, returning
+/// `SLIDEVID_OK` or one of the other `SLIDEVID_*` codes, with the details available from
+/// [`slidevid_last_error_message`].
+///
+/// # Safety
+///
+/// `zip` must point to `zip_len` readable bytes and `frames` to `frame_count` frames, whose
+/// filenames, like `output_path`, are NUL-terminated UTF-8 strings.
+#[no_mangle]
+pub unsafe extern "C" fn slidevid_convert(
+    zip: *const u8,
+    zip_len: usize,
+    frames: *const SlidevidFrame,
+    frame_count: usize,
+    output_path: *const c_char,
+) -> c_int {
+    call(|| convert_raw(zip, zip_len, frames, frame_count, output_path))
+}
+
+/// Runs `f`, storing its error for [`slidevid_last_error_message`] and returning its code.
+pub(crate) fn call(f: impl FnOnce() -> Result<()>) -> c_int {
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    let (code, message) = match result {
+        Ok(Ok(())) => (SLIDEVID_OK, None),
+        Ok(Err(err)) => (error_code(&err), Some(format!("{:#}", err))),
+        Err(_) => (SLIDEVID_PANIC, Some("slidevid panicked".to_owned())),
+    };
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = message
+            .map(|message| CString::new(message.replace('\0', "")).expect("NUL bytes were removed"))
+    });
+    code
+}
+
+/// The message of the last error on this thread, or null if the last call succeeded. It stays
+/// valid until the next call into slidevid on the same thread.
+#[no_mangle]
+pub extern "C" fn slidevid_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+unsafe fn convert_raw(
+    zip: *const u8,
+    zip_len: usize,
+    frames: *const SlidevidFrame,
+    frame_count: usize,
+    output_path: *const c_char,
+) -> Result<()> {
+    if output_path.is_null() {
+        return Err(anyhow!("Null pointer argument"));
+    }
+    let (zip, frames) = read_input(zip, zip_len, frames, frame_count)?;
+    let output_path = CStr::from_ptr(output_path).to_str()?;
+    convert(zip, &frames, output_path, &ConvertOptions::default())?;
+    Ok(())
+}
+
+/// The archive and frames of a call, borrowed from the caller's memory.
+pub(crate) unsafe fn read_input<'a>(
+    zip: *const u8,
+    zip_len: usize,
+    frames: *const SlidevidFrame,
+    frame_count: usize,
+) -> Result<(&'a [u8], Vec<Frame<&'a str>>)> {
+    if zip.is_null() || frames.is_null() {
+        return Err(anyhow!("Null pointer argument"));
+    }
+    let zip = slice::from_raw_parts(zip, zip_len);
+    let frames = slice::from_raw_parts(frames, frame_count)
+        .iter()
+        .map(|frame| {
+            if frame.filename.is_null() {
+                return Err(anyhow!("Null frame filename"));
+            }
+            Ok(Frame::new(
+                CStr::from_ptr(frame.filename).to_str()?,
+                frame.delay_ms,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((zip, frames))
+}
+
+fn error_code(err: &anyhow::Error) -> c_int {
+    match err.downcast_ref() {
+        Some(SlidevidError::Cancelled) => SLIDEVID_CANCELLED,
+        Some(SlidevidError::EncryptedArchive(_)) => SLIDEVID_ENCRYPTED_ARCHIVE,
+        Some(SlidevidError::EmptyArchive) => SLIDEVID_EMPTY_ARCHIVE,
+        Some(SlidevidError::FrameTooLarge(..)) => SLIDEVID_FRAME_TOO_LARGE,
+        Some(SlidevidError::WrongPassword(_)) => SLIDEVID_WRONG_PASSWORD,
+        Some(SlidevidError::MissingFrame { .. }) => SLIDEVID_MISSING_FRAME,
+        Some(SlidevidError::CodecNotFound(_)) => SLIDEVID_CODEC_NOT_FOUND,
+        Some(SlidevidError::InvalidDelay { .. }) => SLIDEVID_INVALID_DELAY,
+        Some(SlidevidError::EncoderError(_))
+        | Some(SlidevidError::IoError(_))
+        | Some(SlidevidError::Other(_))
+        | None => SLIDEVID_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{make_test_zip, make_zip, TempDir};
+
+    fn convert_with(zip: &[u8], frames: &[(&str, u32)], output: &str) -> (c_int, Option<String>) {
+        let names: Vec<_> = frames
+            .iter()
+            .map(|&(name, _)| CString::new(name).unwrap())
+            .collect();
+        let frames: Vec<_> = names
+            .iter()
+            .zip(frames)
+            .map(|(name, &(_, delay_ms))| SlidevidFrame {
+                filename: name.as_ptr(),
+                delay_ms,
+            })
+            .collect();
+        let output = CString::new(output).unwrap();
+        let code = unsafe {
+            slidevid_convert(
+                zip.as_ptr(),
+                zip.len(),
+                frames.as_ptr(),
+                frames.len(),
+                output.as_ptr(),
+            )
+        };
+        let message = slidevid_last_error_message();
+        let message = (!message.is_null()).then(|| {
+            unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned()
+        });
+        (code, message)
+    }
+
+    #[test]
+    fn reports_errors_by_code_and_message() {
+        let code = unsafe { slidevid_convert(ptr::null(), 0, ptr::null(), 0, ptr::null()) };
+        assert_eq!(code, SLIDEVID_ERROR);
+        let message = unsafe { CStr::from_ptr(slidevid_last_error_message()) };
+        assert_eq!(message.to_str().unwrap(), "Null pointer argument");
+        let dir = TempDir::new();
+        let output = dir.join("out.mp4");
+        let output = output.to_str().unwrap();
+        let zip = make_zip(&[("0.png", b"")]);
+        let (code, message) = convert_with(&zip, &[("0.png", 0)], output);
+        assert_eq!(code, SLIDEVID_INVALID_DELAY);
+        assert!(message.unwrap().contains("Frame #0"));
+    }
+
+    #[test]
+    fn converts_and_clears_the_last_error() {
+        let zip = make_test_zip(&[(64, 48, "png")]);
+        let dir = TempDir::new();
+        let output = dir.join("out.mp4");
+        let output = output.to_str().unwrap();
+        let (code, message) = convert_with(&zip, &[("1.png", 100)], output);
+        assert_eq!(code, SLIDEVID_MISSING_FRAME);
+        assert!(message.unwrap().contains("1.png"));
+        assert_eq!(
+            convert_with(&zip, &[("0.png", 100)], output),
+            (SLIDEVID_OK, None)
+        );
+    }
+
+    #[test]
+    fn the_header_matches_the_codes() {
+        let header = include_str!("../include/slidevid.h");
+        let codes = [
+            ("SLIDEVID_OK", SLIDEVID_OK),
+            ("SLIDEVID_ERROR", SLIDEVID_ERROR),
+            ("SLIDEVID_CANCELLED", SLIDEVID_CANCELLED),
+            ("SLIDEVID_ENCRYPTED_ARCHIVE", SLIDEVID_ENCRYPTED_ARCHIVE),
+            ("SLIDEVID_EMPTY_ARCHIVE", SLIDEVID_EMPTY_ARCHIVE),
+            ("SLIDEVID_PANIC", SLIDEVID_PANIC),
+            ("SLIDEVID_FRAME_TOO_LARGE", SLIDEVID_FRAME_TOO_LARGE),
+            ("SLIDEVID_WRONG_PASSWORD", SLIDEVID_WRONG_PASSWORD),
+            ("SLIDEVID_MISSING_FRAME", SLIDEVID_MISSING_FRAME),
+            ("SLIDEVID_CODEC_NOT_FOUND", SLIDEVID_CODEC_NOT_FOUND),
+            ("SLIDEVID_INVALID_DELAY", SLIDEVID_INVALID_DELAY),
+        ];
+        for (name, code) in codes.iter() {
+            let define = format!("#define {} {}\n", name, code);
+            assert!(header.contains(&define), "{} is missing", define.trim());
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{Frame, FrameSource, ZipSource};
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Seek};
+
+/// SHA-256 of each frame's file and delay, in order, to tell whether a deck has changed since
+/// it was last converted. The files are hashed as stored, without decoding them.
+pub fn hash_zip_frames<Z: Read + Seek, S: AsRef<str>>(
+    zip: Z,
+    frames: &[Frame<S>],
+) -> Result<[u8; 32]> {
+    let source = &mut ZipSource::new(zip)?;
+    let mut hasher = Sha256::new();
+    for frame in frames {
+        let data = source.read_frame(frame.filename.as_ref())?;
+        // The length keeps the boundaries between files from shifting without changing the hash.
+        hasher.update((data.len() as u64).to_le_bytes());
+        hasher.update(&data);
+        // So is the delay, written at a fixed width behind whether there is one.
+        hasher.update([frame.delay.is_some() as u8]);
+        hasher.update(frame.delay.unwrap_or(0).to_le_bytes());
+    }
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::make_zip;
+    use std::io::Cursor;
+
+    #[test]
+    fn tells_apart_where_the_delays_are() {
+        let zip = make_zip(&[("a.png", b"one"), ("b.png", b"two")]);
+        let frame = |name, delay| Frame {
+            delay,
+            ..Frame::new(name, 0)
+        };
+        let decks = [
+            [frame("a.png", None), frame("b.png", None)],
+            [frame("a.png", Some(0)), frame("b.png", None)],
+            [frame("a.png", None), frame("b.png", Some(0))],
+            [frame("a.png", Some(0)), frame("b.png", Some(0))],
+            [frame("a.png", Some(1)), frame("b.png", None)],
+        ];
+        let hashes: Vec<_> = decks
+            .iter()
+            .map(|frames| hash_zip_frames(Cursor::new(&zip), frames).unwrap())
+            .collect();
+        for (index, hash) in hashes.iter().enumerate() {
+            assert!(!hashes[index + 1..].contains(hash), "{}", index);
+        }
+        assert_eq!(
+            hashes[0],
+            hash_zip_frames(Cursor::new(&zip), &decks[0]).unwrap()
+        );
+    }
+}
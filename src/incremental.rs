@@ -0,0 +1,164 @@
+use crate::{
+    convert, output::TempOutput, ConvertOptions, EncodeStats, Frame, FrameSource, ZipSource,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// What the last successful encode was made from, as stored in the manifest file.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Manifest {
+    frames: Vec<FrameRecord>,
+    options: serde_json::Value,
+    /// SHA-256, in hex, of the options that `options` can't hold since they aren't serialized.
+    #[serde(default)]
+    unserialized_options: String,
+    output: Option<OutputRecord>,
+}
+
+/// A frame with everything about it that affects the output, its fields flattened so manifests
+/// written before the other fields were recorded still read.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct FrameRecord {
+    #[serde(flatten)]
+    frame: Frame<String>,
+    /// SHA-256 of the frame's file, in hex.
+    hash: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct OutputRecord {
+    path: PathBuf,
+    modified: SystemTime,
+}
+
+/// Converts a deck only when its slides, their settings or the options changed since the last
+/// conversion recorded in a JSON manifest, or when the output was modified or removed since.
+pub struct IncrementalConvert {
+    manifest_path: PathBuf,
+    manifest: Manifest,
+    options: ConvertOptions,
+}
+
+impl IncrementalConvert {
+    /// Loads the manifest at `manifest_path`, starting from an empty one if it doesn't exist.
+    pub fn new(manifest_path: &Path) -> Result<Self> {
+        let manifest = match fs::read(manifest_path) {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Manifest::default(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            manifest_path: manifest_path.to_owned(),
+            manifest,
+            options: ConvertOptions::default(),
+        })
+    }
+
+    pub fn options(mut self, options: ConvertOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Converts the deck unless the output is up to date, returning `None` if it was skipped.
+    pub fn run<Z: AsRef<[u8]>, S: AsRef<str>, O: AsRef<Path>>(
+        &mut self,
+        zip: Z,
+        frames: &[Frame<S>],
+        output_path: O,
+    ) -> Result<Option<EncodeStats>> {
+        let source = &mut ZipSource::with_options(Cursor::new(zip.as_ref()), &self.options)?;
+        let records = frames
+            .iter()
+            .map(|frame| {
+                let data = source.read_frame(frame.filename.as_ref())?;
+                Ok(FrameRecord {
+                    frame: frame.to_owned_frame(),
+                    hash: hex(&Sha256::digest(&data)),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let options = serde_json::to_value(&self.options)?;
+        let unserialized_options = hash_unserialized(&self.options);
+        if self.manifest.frames == records
+            && self.manifest.options == options
+            && self.manifest.unserialized_options == unserialized_options
+            && self.manifest.output.as_ref().is_some_and(is_current)
+        {
+            return Ok(None);
+        }
+        let stats = convert(zip.as_ref(), frames, output_path, &self.options)?;
+        let manifest = Manifest {
+            frames: records,
+            options,
+            unserialized_options,
+            output: Some(OutputRecord {
+                path: stats.output_path.clone(),
+                modified: fs::metadata(&stats.output_path)?.modified()?,
+            }),
+        };
+        let temp = TempOutput::new(&self.manifest_path);
+        fs::write(temp.path(), serde_json::to_vec_pretty(&manifest)?)?;
+        temp.commit()?;
+        self.manifest = manifest;
+        Ok(Some(stats))
+    }
+}
+
+/// Hashes the options serde skips, the cancellation token aside since it doesn't change the
+/// output.
+fn hash_unserialized(options: &ConvertOptions) -> String {
+    let unserialized = format!(
+        "{:?}",
+        (
+            options.codec,
+            options.force_input_codec,
+            options.sample_aspect_ratio,
+            options.decoder_time_base,
+            options.output_time_base,
+            &options.password,
+        )
+    );
+    hex(&Sha256::digest(unserialized.as_bytes()))
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn is_current(output: &OutputRecord) -> bool {
+    fs::metadata(&output.path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified == output.modified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ffmpeg::codec;
+
+    #[test]
+    fn unserialized_options_change_the_hash() {
+        let options = ConvertOptions::new();
+        let hash = hash_unserialized(&options);
+        assert_eq!(hash, hash_unserialized(&options.clone()));
+        for changed in [
+            options.clone().codec(codec::Id::HEVC),
+            options.clone().force_input_codec(codec::Id::PNG),
+            options.clone().sample_aspect_ratio((4, 3)),
+            options.clone().decoder_time_base((1, 90_000)),
+            options.clone().output_time_base((1, 1_000)),
+            options.clone().password("secret"),
+        ]
+        .iter()
+        {
+            assert_ne!(hash_unserialized(changed), hash);
+        }
+    }
+}
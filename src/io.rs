@@ -0,0 +1,303 @@
+use crate::{encode_into, spec, ConvertOptions, EncodeStats, Frame, Pass, ZipSource};
+use anyhow::{anyhow, Result};
+use ffmpeg::{
+    format,
+    util::error::{EINVAL, EIO},
+};
+use std::{
+    ffi::CString,
+    io::{Cursor, Seek, SeekFrom, Write},
+    os::raw::{c_int, c_void},
+    ptr, slice,
+};
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+// From <stdio.h>, which is what FFmpeg passes as `whence`.
+const SEEK_SET: c_int = 0;
+const SEEK_CUR: c_int = 1;
+const SEEK_END: c_int = 2;
+
+pub fn encode_to_stream<W: Write + Seek, Z: AsRef<[u8]>, S: AsRef<str>>(
+    writer: W,
+    format_name: &str,
+    zip: Z,
+    frames: &[Frame<S>],
+) -> Result<EncodeStats> {
+    encode_to_stream_with(writer, format_name, zip, frames, &ConvertOptions::default())
+}
+
+pub fn encode_to_stream_with<W: Write + Seek, Z: AsRef<[u8]>, S: AsRef<str>>(
+    writer: W,
+    format_name: &str,
+    zip: Z,
+    frames: &[Frame<S>],
+    options: &ConvertOptions,
+) -> Result<EncodeStats> {
+    if options.rate_control.is_two_pass() {
+        return Err(anyhow!("Two-pass encoding needs a file output"));
+    }
+    let source = &mut ZipSource::with_options(Cursor::new(zip.as_ref()), options)?;
+    if options.dry_run {
+        return spec::plan(source, frames, options);
+    }
+    let mut output = StreamOutput::new(writer, format_name, Some(seek::<W>))?;
+    encode_into(source, frames, &mut output.output.0, options, Pass::Single)
+}
+
+/// Encodes into a writer that can't seek, in `options.container`, which has to be one that can
+/// be written front to back such as [`Container::FragmentedMp4`].
+///
+/// [`Container::FragmentedMp4`]: crate::Container::FragmentedMp4
+pub fn encode_to_writer<W: Write, Z: AsRef<[u8]>, S: AsRef<str>>(
+    writer: W,
+    zip: Z,
+    frames: &[Frame<S>],
+    options: &ConvertOptions,
+) -> Result<EncodeStats> {
+    if options.rate_control.is_two_pass() {
+        return Err(anyhow!("Two-pass encoding needs a file output"));
+    }
+    if !options.container.is_streamable() {
+        return Err(anyhow!(
+            "The {:?} container needs a seekable output",
+            options.container
+        ));
+    }
+    let source = &mut ZipSource::with_options(Cursor::new(zip.as_ref()), options)?;
+    if options.dry_run {
+        return spec::plan(source, frames, options);
+    }
+    let mut output = StreamOutput::new(writer, options.container.format_name(), None)?;
+    encode_into(source, frames, &mut output.output.0, options, Pass::Single)
+}
+
+/// Encodes into an `AVIOContext` the caller built, e.g. with `avio_alloc_context` and their own
+/// write and seek callbacks, for IO that doesn't fit [`encode_to_stream_with`].
+///
+/// # Safety
+///
+/// `avio` must be a valid, writable `AVIOContext`, and its `opaque` pointer and callbacks must
+/// stay valid until this returns. They're only called from the calling thread. The context is
+/// left open, for the caller to free once this returns.
+pub unsafe fn encode_to_avio<Z: AsRef<[u8]>, S: AsRef<str>>(
+    avio: *mut ffmpeg_sys::AVIOContext,
+    format_name: &str,
+    zip: Z,
+    frames: &[Frame<S>],
+    options: &ConvertOptions,
+) -> Result<EncodeStats> {
+    if options.rate_control.is_two_pass() {
+        return Err(anyhow!("Two-pass encoding needs a file output"));
+    }
+    let source = &mut ZipSource::with_options(Cursor::new(zip.as_ref()), options)?;
+    if options.dry_run {
+        return spec::plan(source, frames, options);
+    }
+    let mut output = AvioOutput::new(avio, format_name)?;
+    encode_into(source, frames, &mut output.0, options, Pass::Single)
+}
+
+/// An output context writing through an AVIO context it doesn't own.
+struct AvioOutput(format::context::Output);
+
+impl AvioOutput {
+    unsafe fn new(avio: *mut ffmpeg_sys::AVIOContext, format_name: &str) -> Result<Self> {
+        let format_name = CString::new(format_name)?;
+        let mut ctx = ptr::null_mut();
+        let err = ffmpeg_sys::avformat_alloc_output_context2(
+            &mut ctx,
+            ptr::null_mut(),
+            format_name.as_ptr(),
+            ptr::null(),
+        );
+        if err < 0 {
+            return Err(ffmpeg::Error::from(err).into());
+        }
+        (*ctx).pb = avio;
+        (*ctx).flags |= ffmpeg_sys::AVFMT_FLAG_CUSTOM_IO as c_int;
+        Ok(Self(format::context::Output::wrap(ctx)))
+    }
+}
+
+impl Drop for AvioOutput {
+    fn drop(&mut self) {
+        // The output context would otherwise `avio_close` a context it doesn't own.
+        unsafe { (*self.0.as_mut_ptr()).pb = ptr::null_mut() };
+    }
+}
+
+/// An output context writing through a custom AVIO context into `W` instead of a file.
+struct StreamOutput<W> {
+    output: AvioOutput,
+    avio: *mut ffmpeg_sys::AVIOContext,
+    _writer: Box<W>,
+}
+
+type SeekFn = unsafe extern "C" fn(*mut c_void, i64, c_int) -> i64;
+
+impl<W: Write> StreamOutput<W> {
+    /// Without `seek` the muxer is told the output isn't seekable.
+    fn new(writer: W, format_name: &str, seek: Option<SeekFn>) -> Result<Self> {
+        let mut writer = Box::new(writer);
+        unsafe {
+            let buffer = ffmpeg_sys::av_malloc(BUFFER_SIZE) as *mut u8;
+            if buffer.is_null() {
+                return Err(anyhow!("Couldn't allocate the AVIO buffer"));
+            }
+            let avio = ffmpeg_sys::avio_alloc_context(
+                buffer,
+                BUFFER_SIZE as c_int,
+                1,
+                &mut *writer as *mut W as *mut c_void,
+                None,
+                Some(write_packet::<W>),
+                seek,
+            );
+            if avio.is_null() {
+                ffmpeg_sys::av_free(buffer as *mut c_void);
+                return Err(anyhow!("Couldn't allocate the AVIO context"));
+            }
+            let output = match AvioOutput::new(avio, format_name) {
+                Ok(output) => output,
+                Err(err) => {
+                    free_avio(avio);
+                    return Err(err);
+                }
+            };
+            Ok(Self {
+                output,
+                avio,
+                _writer: writer,
+            })
+        }
+    }
+}
+
+impl<W> Drop for StreamOutput<W> {
+    fn drop(&mut self) {
+        unsafe { free_avio(self.avio) };
+    }
+}
+
+unsafe fn free_avio(mut avio: *mut ffmpeg_sys::AVIOContext) {
+    ffmpeg_sys::avio_flush(avio);
+    ffmpeg_sys::av_freep(&mut (*avio).buffer as *mut *mut u8 as *mut c_void);
+    ffmpeg_sys::avio_context_free(&mut avio);
+}
+
+unsafe extern "C" fn write_packet<W: Write>(
+    opaque: *mut c_void,
+    buf: *mut u8,
+    size: c_int,
+) -> c_int {
+    let writer = &mut *(opaque as *mut W);
+    match writer.write_all(slice::from_raw_parts(buf, size as usize)) {
+        Ok(()) => size,
+        Err(_) => ffmpeg::Error::Other { errno: EIO }.into(),
+    }
+}
+
+unsafe extern "C" fn seek<W: Seek>(opaque: *mut c_void, offset: i64, whence: c_int) -> i64 {
+    let writer = &mut *(opaque as *mut W);
+    let whence = whence & !(ffmpeg_sys::AVSEEK_FORCE as c_int);
+    let pos = match whence {
+        SEEK_SET => SeekFrom::Start(offset as u64),
+        SEEK_CUR => SeekFrom::Current(offset),
+        SEEK_END => SeekFrom::End(offset),
+        _ if whence == ffmpeg_sys::AVSEEK_SIZE as c_int => {
+            let size = writer.stream_position().and_then(|pos| {
+                let end = writer.seek(SeekFrom::End(0))?;
+                writer.seek(SeekFrom::Start(pos))?;
+                Ok(end)
+            });
+            return size.map_or(-1, |size| size as i64);
+        }
+        _ => return c_int::from(ffmpeg::Error::Other { errno: EINVAL }) as i64,
+    };
+    match writer.seek(pos) {
+        Ok(pos) => pos as i64,
+        Err(_) => c_int::from(ffmpeg::Error::Other { errno: EIO }) as i64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{make_test_zip, test_frames, Probe, TempDir};
+    use crate::Container;
+    use std::convert::TryInto;
+
+    /// A writer that can only append, like a pipe.
+    struct AppendOnly(Vec<u8>);
+
+    impl Write for AppendOnly {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writes_fragments_front_to_back() {
+        let slides = [(64, 48, "png"), (64, 48, "jpg"), (64, 48, "png")];
+        let mut writer = AppendOnly(Vec::new());
+        let options = ConvertOptions::new()
+            .container(Container::FragmentedMp4)
+            .keyframe_per_slide(true);
+        let zip = make_test_zip(&slides);
+        encode_to_writer(&mut writer, zip, &test_frames(&slides, 100), &options).unwrap();
+        let (data, mut boxes, mut pos) = (writer.0, Vec::new(), 0);
+        while pos + 8 <= data.len() {
+            let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            assert!(size >= 8);
+            boxes.push(&data[pos + 4..pos + 8]);
+            pos += size;
+        }
+        assert_eq!(pos, data.len());
+        // Every slide starts a fragment, after a `moov` without any samples.
+        assert_eq!(&boxes[..2], [b"ftyp", b"moov"]);
+        assert_eq!(boxes.iter().filter(|&&name| name == b"moof").count(), 3);
+        assert_eq!(boxes.last().unwrap(), b"mfra");
+        let dir = TempDir::new();
+        let output = dir.join("out.mp4");
+        std::fs::write(&output, &data).unwrap();
+        assert_eq!(Probe::new(&output).frames().len(), 3);
+    }
+
+    #[test]
+    fn encodes_through_the_callers_avio_context() {
+        let slides = [(64, 48, "png"), (64, 48, "jpg")];
+        let mut buffer = Cursor::new(Vec::new());
+        unsafe {
+            let data = ffmpeg_sys::av_malloc(BUFFER_SIZE) as *mut u8;
+            let avio = ffmpeg_sys::avio_alloc_context(
+                data,
+                BUFFER_SIZE as c_int,
+                1,
+                &mut buffer as *mut Cursor<Vec<u8>> as *mut c_void,
+                None,
+                Some(write_packet::<Cursor<Vec<u8>>>),
+                Some(seek::<Cursor<Vec<u8>>>),
+            );
+            assert!(!avio.is_null());
+            let result = encode_to_avio(
+                avio,
+                "mp4",
+                make_test_zip(&slides),
+                &test_frames(&slides, 100),
+                &ConvertOptions::new(),
+            );
+            free_avio(avio);
+            result.unwrap();
+        }
+        let dir = TempDir::new();
+        let output = dir.join("out.mp4");
+        std::fs::write(&output, buffer.into_inner()).unwrap();
+        let mut probe = Probe::new(&output);
+        assert_eq!(probe.frames().len(), 2);
+    }
+}
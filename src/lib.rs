@@ -1,51 +1,595 @@
 use anyhow::{anyhow, Result};
 use ffmpeg::{
-    codec, decoder, encoder, format, frame, picture, software::scaling, util::error, Packet,
-    Rational,
+    codec, decoder, encoder, format, frame, picture, software::scaling, threading, Dictionary,
+    Packet, Rational, Rescale,
 };
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{
-    io::{Cursor, Read},
+    collections::VecDeque,
+    io::Cursor,
     result::Result as StdResult,
+    time::{Duration, Instant},
 };
-use zip::{read::ZipFile, ZipArchive};
 
+mod alpha;
+#[cfg(feature = "tokio")]
+mod async_io;
+mod audio;
+mod batch;
+mod cancel;
+mod chapters;
+mod color;
+mod container;
+mod converter;
+mod crop;
+mod decode;
+mod effect;
+mod error;
+mod extract;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod fingerprint;
+#[cfg(feature = "incremental")]
+mod incremental;
+mod io;
+mod options;
+mod output;
+mod palette;
+mod pixel;
+mod profile;
+mod rate;
+mod rendition;
+#[cfg(feature = "report")]
+mod report;
+mod scale;
+mod sei;
+mod sidecar;
+mod sort;
+mod source;
+mod spec;
+mod stats;
+mod streaming;
+mod subtitle;
+#[cfg(test)]
+mod test_util;
+#[cfg(test)]
+mod tests;
+mod timing;
+mod transform;
+mod validate;
+mod verify;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use alpha::Rgb;
+#[cfg(feature = "tokio")]
+pub use async_io::convert_to_mp4_async;
+pub use batch::{convert_batch, Job};
+pub use cancel::CancellationToken;
+pub use chapters::split_video;
+pub use color::{ColorPrimaries, ColorRange, ColorSpace, ColorTrc};
+pub use container::{validate_output_format, Container};
+pub use converter::Converter;
+pub use crop::Rect;
+pub use decode::{decode_frames, decode_frames_with, DecodedFrame, FrameIterator};
+pub use effect::ColorFilter;
+pub use error::SlidevidError;
+pub use extract::{video_to_zip, ImageFormat};
+pub use fingerprint::hash_zip_frames;
+#[cfg(feature = "incremental")]
+pub use incremental::IncrementalConvert;
+pub use io::{encode_to_avio, encode_to_stream, encode_to_stream_with, encode_to_writer};
+pub use options::ConvertOptions;
+pub use output::WriteMode;
+pub use pixel::{BitDepth, PixelFormat};
+pub use profile::{H265Profile, Level, Profile};
+use rate::Pass;
+pub use rate::RateControl;
+pub use rendition::{convert_multi_rendition, Rendition};
+#[cfg(feature = "report")]
+pub use report::EncodeReport;
+pub use scale::ScaleAlgorithm;
+pub use sei::SEI_UUID;
+pub use sidecar::{generate_ass, generate_srt, write_srt};
+pub use sort::{natural_cmp, natural_sort};
+#[cfg(feature = "image")]
+pub use source::ImageSource;
+#[cfg(feature = "sevenz")]
+pub use source::SevenZipSource;
+#[cfg(feature = "tar")]
+pub use source::TarSource;
+pub use source::{DirSource, FrameSource, ZipSource};
+pub use spec::EncodeSpec;
+pub use stats::{EncodeStats, FrameTiming};
+pub use streaming::SlideEncoder;
+pub use subtitle::{SubtitlePosition, SubtitleStyle};
+pub use timing::{estimate, estimate_with, frames_from_timestamps, EstimatedOutput, TimingMode};
+pub use transform::{LetterboxFill, Transform};
+pub use validate::{validate, SlideInfo, ValidationReport};
+pub use verify::{verify_output, DelayMismatch, VerifyReport};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Frame<S: AsRef<str>> {
     filename: S,
-    delay: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    delay: Option<u32>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    metadata: Option<Vec<u8>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    chapter_title: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    subtitle: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    scaling: Option<ScaleAlgorithm>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    size: Option<(u32, u32)>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    transform: Option<Transform>,
 }
 
+impl<S: AsRef<str>> Frame<S> {
+    pub fn new(filename: S, delay: u32) -> Self {
+        Self {
+            delay: Some(delay),
+            ..Self::without_delay(filename)
+        }
+    }
+
+    /// A frame lasting [`ConvertOptions::default_delay_ms`].
+    pub fn without_delay(filename: S) -> Self {
+        Self {
+            filename,
+            delay: None,
+            metadata: None,
+            chapter_title: None,
+            subtitle: None,
+            scaling: None,
+            size: None,
+            transform: None,
+        }
+    }
+
+    /// Embeds `metadata` as an H.264 user data unregistered SEI message tagged with
+    /// [`SEI_UUID`] on this frame.
+    pub fn with_metadata(mut self, metadata: Vec<u8>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Starts a new chapter named `title` at this frame.
+    pub fn with_chapter_title(mut self, title: impl Into<String>) -> Self {
+        self.chapter_title = Some(title.into());
+        self
+    }
+
+    /// Shows `subtitle` while this frame is on screen, burned in styled by
+    /// [`ConvertOptions::subtitle_style`] unless [`ConvertOptions::burn_subtitles`] is off, and
+    /// as a cue of [`generate_srt`], [`generate_ass`] and [`write_srt`].
+    pub fn with_subtitle(mut self, subtitle: impl Into<String>) -> Self {
+        self.subtitle = Some(subtitle.into());
+        self
+    }
+
+    /// Scales this frame with `algorithm` instead of the default Lanczos.
+    pub fn with_scaling(mut self, algorithm: ScaleAlgorithm) -> Self {
+        self.scaling = Some(algorithm);
+        self
+    }
+
+    /// Scales this frame to `width` by `height` instead of the output size, centered over
+    /// [`ConvertOptions::letterbox_fill`]. The size must fit within the output's.
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    /// Applies `transform` to this frame instead of [`ConvertOptions::transform`], fitting the
+    /// result into the first slide's size with [`ConvertOptions::letterbox_fill`] bars where
+    /// the aspect ratios differ.
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+
+    /// Checks for filenames no zip archive could contain. Zero delays are reported by the
+    /// conversion, as [`SlidevidError::InvalidDelay`] with the frame's index.
+    pub fn validate(&self) -> Result<()> {
+        let filename = self.filename.as_ref();
+        if filename.is_empty() {
+            return Err(anyhow!("Frame has an empty filename"));
+        }
+        if filename.contains('\0') {
+            return Err(anyhow!("Frame filename {:?} contains a NUL byte", filename));
+        }
+        if filename.len() > u16::MAX as usize {
+            return Err(anyhow!(
+                "Frame filename is {} bytes, over the zip limit of {} bytes",
+                filename.len(),
+                u16::MAX
+            ));
+        }
+        Ok(())
+    }
+
+    /// How long this frame lasts in milliseconds, falling back to `default`.
+    pub(crate) fn delay_or(&self, default: Option<u32>) -> Result<u32> {
+        self.delay.or(default).ok_or_else(|| {
+            anyhow!(
+                "Frame {:?} has no delay and no default delay is set",
+                self.filename.as_ref()
+            )
+        })
+    }
+
+    /// This frame with its filename copied, for keeping past the borrowed deck.
+    #[cfg(any(feature = "tokio", feature = "incremental"))]
+    pub(crate) fn to_owned_frame(&self) -> Frame<String> {
+        Frame {
+            filename: self.filename.as_ref().to_owned(),
+            delay: self.delay,
+            metadata: self.metadata.clone(),
+            chapter_title: self.chapter_title.clone(),
+            subtitle: self.subtitle.clone(),
+            scaling: self.scaling,
+            size: self.size,
+            transform: self.transform,
+        }
+    }
+
+    /// `frames` in reverse order, borrowing the filenames so the deck can be encoded backwards.
+    pub fn reversed(frames: &[Frame<S>]) -> Vec<Frame<&S>> {
+        frames
+            .iter()
+            .rev()
+            .map(|frame| Frame {
+                filename: &frame.filename,
+                delay: frame.delay,
+                metadata: frame.metadata.clone(),
+                chapter_title: frame.chapter_title.clone(),
+                subtitle: frame.subtitle.clone(),
+                scaling: frame.scaling,
+                size: frame.size,
+                transform: frame.transform,
+            })
+            .collect()
+    }
+}
+
+/// CRF of the default [`RateControl`], from 0 (lossless) to 51. Slides are mostly flat areas
+/// and sharp text, which 18 keeps visually lossless at a fraction of the size of lower values.
+pub const DEFAULT_CRF: u8 = 18;
+/// x264 preset every encode uses. Slide shows have few frames, so the slowest preset's smaller
+/// output costs little time.
+pub const DEFAULT_PRESET: &str = "veryslow";
+
 const MILLIS: i32 = 1_000;
 const DECODER_TIME_BASE: Rational = Rational(1, MILLIS);
 const OUTPUT_TIME_BASE: Rational = Rational(1, 90_000);
+const NULL_DEVICE: &str = if cfg!(windows) { "NUL" } else { "/dev/null" };
 
 const LANCZOS: scaling::Flags = scaling::Flags::LANCZOS;
-const YUV420P: format::Pixel = format::Pixel::YUV420P;
 
+fn decoder_id(filename: &str) -> codec::Id {
+    let name = filename.as_bytes();
+    let has_extension = |ext: &[u8]| {
+        name.len() >= ext.len() && name[name.len() - ext.len()..].eq_ignore_ascii_case(ext)
+    };
+    if has_extension(b"png") {
+        codec::Id::PNG
+    } else if has_extension(b"pam") {
+        codec::Id::PAM
+    } else if has_extension(b"ppm") {
+        codec::Id::PPM
+    } else {
+        codec::Id::MJPEG
+    }
+}
+
+fn open_decoder_as(id: codec::Id) -> Result<decoder::Opened> {
+    Ok(codec::Context::new()
+        .decoder()
+        .open_as(codec::decoder::find(id).ok_or(SlidevidError::CodecNotFound(id))?)?)
+}
+
+/// Reads and decodes the slide `filename` on its own, the way a conversion with `options` would.
+fn decode_slide<F: FrameSource>(
+    source: &mut F,
+    filename: &str,
+    options: &ConvertOptions,
+) -> Result<frame::Video> {
+    if let Some(decoded) = source.read_decoded(filename)? {
+        return Ok(decoded);
+    }
+    let data = source.read_frame(filename)?;
+    let id = options
+        .force_input_codec
+        .unwrap_or_else(|| decoder_id(filename));
+    let mut decoder = open_decoder_as(id)?;
+    decoder.send_packet(&image_packet(&data))?;
+    decoder.send_eof()?;
+    let mut decoded = frame::Video::empty();
+    decoder.receive_frame(&mut decoded)?;
+    Ok(decoded)
+}
+
+/// Like [`open_decoder_as`], with [`ConvertOptions::decoder_threads`] frame threads if set.
+fn open_decoder_with(id: codec::Id, options: &ConvertOptions) -> Result<decoder::Opened> {
+    let mut context = codec::Context::new();
+    if let Some(threads) = options.decoder_threads {
+        context.set_threading(threading::Config {
+            kind: threading::Type::Frame,
+            count: threads.get() as usize,
+            safe: false,
+        });
+    }
+    Ok(context
+        .decoder()
+        .open_as(codec::decoder::find(id).ok_or(SlidevidError::CodecNotFound(id))?)?)
+}
+
+/// Creates a scaler to `height`, keeping the aspect ratio, or to the source's own size.
+fn create_scaler(
+    source: &frame::Video,
+    options: &ConvertOptions,
+    height: Option<u32>,
+) -> Result<(scaling::Context, frame::Video)> {
+    let (src_w, src_h) = (source.width(), source.height());
+    let (width, height) = match height {
+        Some(height) => (
+            (src_w as u64 * height as u64 / src_h as u64).max(1) as u32,
+            height,
+        ),
+        None => (src_w, src_h),
+    };
+    let (shift_w, shift_h) = match options.codec_id() {
+        codec::Id::GIF => (0, 0),
+        _ => options.pixel_format.chroma_shift(),
+    };
+    if let Some((width, height)) = options.output_size {
+        if width == 0
+            || height == 0
+            || !width.is_multiple_of(1 << shift_w)
+            || !height.is_multiple_of(1 << shift_h)
+        {
+            return Err(anyhow!(
+                "Output size {}x{} isn't a whole number of {:?} chroma samples",
+                width,
+                height,
+                options.pixel_format
+            ));
+        }
+    }
+    let (dst_w, dst_h) = (
+        width.next_multiple_of(1 << shift_w),
+        height.next_multiple_of(1 << shift_h),
+    );
+    let mut scaler = scaling::Context::get(
+        source.format(),
+        src_w,
+        src_h,
+        scaled_format(options),
+        dst_w,
+        dst_h,
+        LANCZOS,
+    )?;
+    color::configure_scaler(&mut scaler, options);
+    let scaled = frame::Video::new(scaled_format(options), dst_w, dst_h);
+    Ok((scaler, scaled))
+}
+
+/// Pixel format slides are scaled to, which is the encoder's own except for GIF, where they're
+/// only quantized to a palette after.
+fn scaled_format(options: &ConvertOptions) -> format::Pixel {
+    match options.codec_id() {
+        codec::Id::GIF => format::Pixel::BGRA,
+        _ => options.pixel_format.pixel(),
+    }
+}
+
+/// Pixel format of the frames sent to the encoder.
+fn encoded_format(options: &ConvertOptions) -> format::Pixel {
+    match options.codec_id() {
+        codec::Id::GIF => format::Pixel::PAL8,
+        _ => options.pixel_format.pixel(),
+    }
+}
+
+fn find_encoder(options: &ConvertOptions) -> Result<ffmpeg::Codec> {
+    let id = options.codec_id();
+    let preferred = options
+        .preferred_encoder
+        .as_deref()
+        .and_then(codec::encoder::find_by_name)
+        .filter(|codec| codec.id() == id);
+    let codec = preferred
+        .or_else(|| codec::encoder::find(id))
+        .ok_or(SlidevidError::CodecNotFound(id))?;
+    pixel::ensure_supported(codec, encoded_format(options))?;
+    Ok(codec)
+}
+
+fn configure_encoder(
+    encoder: &mut encoder::video::Video,
+    options: &ConvertOptions,
+    pass: Pass,
+    (width, height): (u32, u32),
+    time_base: Rational,
+) -> Result<Dictionary<'static>> {
+    // Without a global header the encoder repeats the parameter sets before every keyframe.
+    let header = if options.container.has_global_header() {
+        codec::Flags::GLOBAL_HEADER
+    } else {
+        codec::Flags::empty()
+    };
+    encoder.set_flags(header | pass.flags());
+    encoder.set_width(width);
+    encoder.set_height(height);
+    let frame_rate = match options.max_fps {
+        Some(max_fps) if f64::from(time_base.invert()) > max_fps as f64 => {
+            Rational(max_fps as i32, 1)
+        }
+        _ => time_base.invert(),
+    };
+    encoder.set_frame_rate(Some(frame_rate));
+    encoder.set_format(encoded_format(options));
+    encoder.set_time_base(time_base);
+    color::tag_encoder(encoder, options);
+    if let Some(gop_size) = options.gop_size {
+        encoder.set_gop(gop_size);
+    }
+    if let Some(bframes) = options.bframes {
+        encoder.set_max_b_frames(bframes as usize);
+    }
+    if let Some(strength) = options.sharpen {
+        if !strength.is_finite() || strength < 0.0 {
+            return Err(anyhow!(
+                "Sharpening strength must be zero or more, got {}",
+                strength
+            ));
+        }
+    }
+    if let Some(sar) = options.sample_aspect_ratio {
+        if sar.numerator() <= 0 || sar.denominator() <= 0 {
+            return Err(anyhow!("Sample aspect ratio must be positive, got {}", sar));
+        }
+        encoder.set_aspect_ratio(sar);
+    }
+    let mut encoder_options = Dictionary::new();
+    match options.codec_id() {
+        // Uncompressed and GIF frames have no quality or rate to tune.
+        codec::Id::RAWVIDEO => return Ok(encoder_options),
+        // The effects work on YUV planes, which GIF frames are never scaled to.
+        codec::Id::GIF if options.filter.is_some() || options.sharpen.is_some() => {
+            return Err(anyhow!(
+                "Color filters and sharpening aren't supported for GIF"
+            ));
+        }
+        codec::Id::GIF => return Ok(encoder_options),
+        _ => {}
+    }
+    encoder_options.set("preset", DEFAULT_PRESET);
+    options.rate_control.validate(options.codec_id())?;
+    options.rate_control.apply(encoder, &mut encoder_options);
+    if let (RateControl::Crf(_), Some(max_bitrate)) = (options.rate_control, options.max_bitrate) {
+        encoder.set_max_bit_rate(max_bitrate);
+        encoder_options.set("bufsize", &(2 * max_bitrate).to_string());
+    }
+    pass.apply(&mut encoder_options);
+    if options.codec_id() == codec::Id::H264 {
+        let profile = options.profile.or(options.pixel_format.h264_profile());
+        if profile == Some(Profile::Baseline) {
+            // Baseline has neither B-frames nor CABAC, which x264 would otherwise use.
+            if options.bframes.is_some_and(|bframes| bframes > 0) {
+                return Err(anyhow!("H.264 profile baseline doesn't support B-frames"));
+            }
+            encoder.set_max_b_frames(0);
+            encoder_options.set("coder", "cavlc");
+        }
+        profile::apply(
+            profile,
+            options.level,
+            options.pixel_format.pixel(),
+            (width, height),
+            frame_rate,
+            &mut encoder_options,
+        )?;
+        if options.keyframe_per_slide {
+            // x264 otherwise only turns forced I-frames into IDR frames when the GOP allows it.
+            encoder_options.set("forced-idr", "1");
+        }
+    }
+    if let (codec::Id::HEVC, Some(profile)) = (options.codec_id(), options.h265_profile) {
+        profile::apply_h265(profile, options.pixel_format.pixel(), &mut encoder_options)?;
+    }
+    Ok(encoder_options)
+}
+
+fn image_packet(data: &[u8]) -> Packet {
+    let mut packet = Packet::copy(data);
+    packet.set_flags(codec::packet::Flags::KEY);
+    packet
+}
+
+/// Sends a slide lasting `delay` milliseconds, advancing `timestamp` in `decoder_time_base`.
 fn send_packet(
     decoder: &mut decoder::Opened,
-    file: &mut ZipFile,
+    data: &[u8],
     timestamp: &mut i64,
-    duration: i64,
+    delay: u32,
+    decoder_time_base: Rational,
     time_base: Rational,
-) -> Result<()> {
-    let mut packet = Packet::new(file.size() as usize);
-    file.read_exact(packet.data_mut().unwrap())?;
-    packet.set_flags(codec::packet::Flags::KEY);
+) -> Result<Duration> {
+    let start = Instant::now();
+    let mut packet = image_packet(data);
+    let duration = (delay as i64).rescale(Rational(1, MILLIS), decoder_time_base);
     packet.set_pts(Some(*timestamp));
     packet.set_duration(duration);
-    packet.rescale_ts(DECODER_TIME_BASE, time_base);
+    packet.rescale_ts(decoder_time_base, time_base);
     *timestamp += duration;
     decoder.send_packet(&packet)?;
-    Ok(())
+    Ok(start.elapsed())
+}
+
+fn receive_frame(
+    decoder: &mut decoder::Opened,
+    decoded: &mut frame::Video,
+) -> Result<Option<Duration>> {
+    let start = Instant::now();
+    Ok(if wrap_result(decoder.receive_frame(decoded))? {
+        Some(start.elapsed())
+    } else {
+        None
+    })
+}
+
+/// A slide scaled for the encoder, with the scratch space the effects reuse from slide to slide.
+struct Scaled {
+    frame: frame::Video,
+    /// Luma samples of the frame, for sharpening.
+    luma: Vec<f32>,
+}
+
+impl Scaled {
+    fn new(frame: frame::Video) -> Self {
+        Self {
+            frame,
+            luma: Vec::new(),
+        }
+    }
 }
 
 fn send_frame(
     encoder: &mut encoder::video::Video,
     decoded: &frame::Video,
     scaler: &mut scaling::Context,
-    scaled: &mut frame::Video,
+    scaled: &mut Scaled,
+    metadata: Option<&[u8]>,
+    options: &ConvertOptions,
+    timing: &mut FrameTiming,
 ) -> Result<()> {
+    let start = Instant::now();
     let src = scaler.input();
     let (src_format, src_w, src_h) = (decoded.format(), decoded.width(), decoded.height());
     if src_format != src.format || src_w != src.width || src_h != src.height {
@@ -53,11 +597,43 @@ fn send_frame(
         scaler.cached(
             src_format, src_w, src_h, dst.format, dst.width, dst.height, LANCZOS,
         );
+        color::configure_scaler(scaler, options);
+    }
+    // The encoder may still hold a reference to the previous frame's buffers, in which case
+    // they're copied; otherwise the buffers allocated up front are reused as is.
+    let err = unsafe { ffmpeg_sys::av_frame_make_writable(scaled.frame.as_mut_ptr()) };
+    if err < 0 {
+        return Err(ffmpeg::Error::from(err).into());
     }
-    scaler.run(decoded, scaled)?;
-    scaled.set_pts(decoded.timestamp());
-    scaled.set_kind(picture::Type::None);
-    encoder.send_frame(scaled)?;
+    scaler.run(decoded, &mut scaled.frame)?;
+    if let Some(filter) = options.filter {
+        effect::apply(&mut scaled.frame, filter, options.pixel_format);
+    }
+    if let Some(strength) = options.sharpen {
+        effect::sharpen(
+            &mut scaled.frame,
+            &mut scaled.luma,
+            strength,
+            options.pixel_format,
+        );
+    }
+    let mut paletted = None;
+    let frame = match options.codec_id() {
+        codec::Id::GIF => paletted.insert(palette::quantize(&scaled.frame)?),
+        _ => &mut scaled.frame,
+    };
+    timing.scale += start.elapsed();
+    frame.set_pts(decoded.timestamp());
+    frame.set_kind(if options.keyframe_per_slide {
+        picture::Type::I
+    } else {
+        picture::Type::None
+    });
+    color::tag_frame(frame, options);
+    sei::attach(frame, metadata)?;
+    let start = Instant::now();
+    encoder.send_frame(frame)?;
+    timing.encode += start.elapsed();
     Ok(())
 }
 
@@ -66,16 +642,21 @@ fn receive_packet(
     output: &mut format::context::Output,
     packet: &mut Packet,
     time_base: Rational,
+    output_time_base: Rational,
+    keyframes: &mut Vec<i64>,
 ) -> Result<()> {
     while wrap_result(encoder.receive_packet(packet))? {
-        packet.rescale_ts(time_base, OUTPUT_TIME_BASE);
+        if packet.is_key() {
+            keyframes.extend(packet.pts());
+        }
+        packet.rescale_ts(time_base, output_time_base);
         packet.write_interleaved(output)?;
     }
     Ok(())
 }
 
-fn wrap_result(result: StdResult<(), error::Error>) -> Result<bool> {
-    use error::{Error::*, EAGAIN};
+fn wrap_result(result: StdResult<(), ffmpeg::Error>) -> Result<bool> {
+    use ffmpeg::util::error::{Error::*, EAGAIN};
     match result {
         Ok(()) => Ok(true),
         Err(Other { errno: EAGAIN }) | Err(Eof) => Ok(false),
@@ -87,90 +668,638 @@ pub fn convert_to_mp4<Z: AsRef<[u8]>, S: AsRef<str>, O: AsRef<Path>>(
     zip: Z,
     frames: &[Frame<S>],
     output_path: O,
-) -> Result<()> {
-    let mut archive = ZipArchive::new(Cursor::new(zip))?;
-    let lowest_delay = frames
+) -> StdResult<EncodeStats, SlidevidError> {
+    Ok(convert(
+        zip,
+        frames,
+        output_path,
+        &ConvertOptions::default(),
+    )?)
+}
+
+pub fn convert<Z: AsRef<[u8]>, S: AsRef<str>, O: AsRef<Path>>(
+    zip: Z,
+    frames: &[Frame<S>],
+    output_path: O,
+    options: &ConvertOptions,
+) -> Result<EncodeStats> {
+    for (index, frame) in frames.iter().enumerate() {
+        if let Some(delay @ 0) = frame.delay {
+            return Err(SlidevidError::InvalidDelay {
+                frame: index,
+                delay,
+            }
+            .into());
+        }
+        frame.validate()?;
+    }
+    if options.reject_mixed_resolution {
+        let source = &mut ZipSource::with_options(Cursor::new(zip.as_ref()), options)?;
+        let selected = timing::select_frames(frames, options)?;
+        let report = validate::validate_from(source, selected, options)?;
+        let mismatched: Vec<_> = report
+            .mismatched_slides()
+            .map(|slide| format!("{} ({}x{})", slide.filename, slide.width, slide.height))
+            .collect();
+        if !mismatched.is_empty() {
+            let first = &report.slides[0];
+            return Err(anyhow!(
+                "Slides differ from the first slide's {}x{}: {}",
+                first.width,
+                first.height,
+                mismatched.join(", ")
+            ));
+        }
+    }
+    let source = &mut ZipSource::with_options(Cursor::new(zip.as_ref()), options)?;
+    convert_from(source, frames, output_path, options)
+}
+
+pub fn convert_many<Z: AsRef<[u8]>, S: AsRef<str>, O: AsRef<Path>>(
+    sources: &[(Z, &[Frame<S>])],
+    output_path: O,
+    options: &ConvertOptions,
+) -> Result<EncodeStats> {
+    let (mut source, frames) = source::ConcatSource::new(sources, options)?;
+    convert_from(&mut source, &frames, output_path, options)
+}
+
+/// Like [`convert_many`], but fails if a segment's slides are a different size than the first
+/// segment's, unless [`ConvertOptions::rescale_segments`] is set to scale them to match.
+pub fn concat_videos<Z: AsRef<[u8]>, S: AsRef<str>, O: AsRef<Path>>(
+    inputs: Vec<(Z, Vec<Frame<S>>)>,
+    output_path: O,
+    options: &ConvertOptions,
+) -> Result<EncodeStats> {
+    if !options.rescale_segments {
+        let mut expected: Option<(u32, u32)> = None;
+        for (index, (zip, frames)) in inputs.iter().enumerate() {
+            let first = &frames[..frames.len().min(1)];
+            let mut decoded = decode_frames_with(Cursor::new(zip.as_ref()), first, options)?;
+            let first = match decoded.next() {
+                Some(decoded) => decoded?.video,
+                None => continue,
+            };
+            let size = (first.width(), first.height());
+            match expected {
+                Some(expected) if expected != size => {
+                    return Err(anyhow!(
+                        "Segment {} is {}x{}, but the first segment is {}x{}",
+                        index,
+                        size.0,
+                        size.1,
+                        expected.0,
+                        expected.1
+                    ))
+                }
+                Some(_) => {}
+                None => expected = Some(size),
+            }
+        }
+    }
+    let sources: Vec<_> = inputs
         .iter()
-        .map(|f| f.delay)
-        .min()
-        .ok_or_else(|| anyhow!("Slide show with 0 frames?!"))? as i32;
-    let mut frames = frames.iter();
-    let frame = frames.next().unwrap();
-    let decoder = &mut codec::Context::new().decoder().open_as(
-        codec::decoder::find({
-            let name = frame.filename.as_ref().as_bytes();
-            if name.len() >= 3 && name[name.len() - 3..].eq_ignore_ascii_case(b"png") {
-                codec::Id::PNG
-            } else {
-                codec::Id::MJPEG
+        .map(|(zip, frames)| (zip, frames.as_slice()))
+        .collect();
+    convert_many(&sources, output_path, options)
+}
+
+pub fn convert_from<F: FrameSource, S: AsRef<str>, O: AsRef<Path>>(
+    source: &mut F,
+    frames: &[Frame<S>],
+    output_path: O,
+    options: &ConvertOptions,
+) -> Result<EncodeStats> {
+    let output_path = output_path.as_ref();
+    let options = &options.for_output(output_path);
+    let result = convert_unreported(source, frames, output_path, options);
+    #[cfg(feature = "report")]
+    let result = report::write(result, frames, options);
+    result
+}
+
+fn convert_unreported<F: FrameSource, S: AsRef<str>>(
+    source: &mut F,
+    frames: &[Frame<S>],
+    output_path: &Path,
+    options: &ConvertOptions,
+) -> Result<EncodeStats> {
+    validate_output_format(output_path, options.container)?;
+    let requested_path = output_path;
+    let mut claim = (!options.discard_output)
+        .then(|| output::resolve_output_path(output_path, options.write_mode))
+        .transpose()?;
+    let output_path = claim
+        .as_ref()
+        .map_or(Path::new(NULL_DEVICE), output::OutputPath::path)
+        .to_owned();
+    if options.dry_run {
+        let mut stats = spec::plan(source, frames, options)?;
+        stats.output_path = output_path;
+        return Ok(stats);
+    }
+    let pass_log = if options.rate_control.is_two_pass() {
+        // Other encoders read and write their stats through the codec context, if at all.
+        let encoder = find_encoder(options)?;
+        if encoder.name() != "libx264" {
+            return Err(anyhow!(
+                "Two-pass encoding is only supported with libx264, not {}",
+                encoder.name()
+            ));
+        }
+        // The null device's directory isn't somewhere to leave the stats file.
+        let pass_log = rate::StatsFile::new(if options.discard_output {
+            requested_path
+        } else {
+            &output_path
+        });
+        encode(
+            source,
+            frames,
+            Path::new(NULL_DEVICE),
+            options,
+            Pass::First(pass_log.path()),
+        )?;
+        Some(pass_log)
+    } else {
+        None
+    };
+    let pass = pass_log
+        .as_ref()
+        .map_or(Pass::Single, |s| Pass::Second(s.path()));
+    let mut stats = if options.atomic && !options.discard_output {
+        let temp = output::TempOutput::new(&output_path);
+        let result = encode(source, frames, temp.path(), options, pass);
+        if error::wrote_output(&result) {
+            temp.commit()?;
+            if let Some(claim) = &mut claim {
+                claim.keep();
             }
-        })
-        .ok_or_else(|| anyhow!("Couldn't find suitable decoder"))?,
-    )?;
+        }
+        result?
+    } else {
+        if let Some(claim) = &mut claim {
+            claim.keep();
+        }
+        encode(source, frames, &output_path, options, pass)?
+    };
+    stats.output_path = output_path;
+    Ok(stats)
+}
+
+fn encode<F: FrameSource, S: AsRef<str>>(
+    source: &mut F,
+    frames: &[Frame<S>],
+    output_path: &Path,
+    options: &ConvertOptions,
+    pass: Pass,
+) -> Result<EncodeStats> {
+    let format_name = match pass {
+        Pass::First(_) => "null",
+        _ => options.container.format_name(),
+    };
+    let output = &mut format::output_as(&output_path, format_name)?;
+    let mut stats = encode_into(source, frames, output, options, pass)?;
+    stats.output_path = output_path.to_owned();
+    Ok(stats)
+}
+
+fn encode_into<F: FrameSource, S: AsRef<str>>(
+    source: &mut F,
+    frames: &[Frame<S>],
+    output: &mut format::context::Output,
+    options: &ConvertOptions,
+    pass: Pass,
+) -> Result<EncodeStats> {
+    let mut stats = encode_renditions(source, frames, vec![(output, None)], options, pass)?;
+    Ok(stats.pop().unwrap())
+}
+
+/// Decodes each slide once and feeds it to a scaler and encoder per output, each scaled to its
+/// height or, for `None`, kept at the slide's own size.
+fn encode_renditions<F: FrameSource, S: AsRef<str>>(
+    source: &mut F,
+    frames: &[Frame<S>],
+    outputs: Vec<(&mut format::context::Output, Option<u32>)>,
+    options: &ConvertOptions,
+    pass: Pass,
+) -> Result<Vec<EncodeStats>> {
+    let frames = timing::select_frames(frames, options)?;
+    let delays = timing::resolve_delays(frames, options)?;
+    let enc_tb = timing::encoder_time_base(&delays, options)?;
+    let renditions = &mut Renditions {
+        outputs,
+        chains: Vec::new(),
+        frames,
+        delays: &delays,
+        first: 0,
+        transformer: transform::Transformer::new(options),
+        frame_timings: Vec::new(),
+        slide_sizes: Vec::new(),
+        last_pts: 0,
+        options,
+        pass,
+        time_base: enc_tb,
+    };
+    let input_codec = |filename: &str| {
+        options
+            .force_input_codec
+            .unwrap_or_else(|| decoder_id(filename))
+    };
+    let mut decoder_kind = input_codec(frames[0].filename.as_ref());
+    let decoder = &mut open_decoder_with(decoder_kind, options)?;
     let ts = &mut 0;
-    let enc_tb = Rational(lowest_delay, MILLIS);
-    send_packet(
-        decoder,
-        &mut archive.by_name(frame.filename.as_ref())?,
-        ts,
-        frame.delay as i64,
-        enc_tb,
-    )?;
+    let pending = &mut VecDeque::new();
     let decoded = &mut frame::Video::empty();
-    let scaled = &mut frame::Video::empty();
-    decoder.receive_frame(decoded)?;
-    let (src_w, src_h) = (decoded.width(), decoded.height());
-    let (dst_w, dst_h) = (src_w + src_w % 2, src_h + src_h % 2);
-    let scaler = &mut scaling::Context::get(
-        decoded.format(),
-        src_w,
-        src_h,
-        YUV420P,
-        dst_w,
-        dst_h,
-        LANCZOS,
-    )?;
-    let output = &mut format::output(&output_path)?;
-    let mut stream = output.add_stream(
-        codec::encoder::find(codec::Id::H264)
-            .ok_or_else(|| anyhow!("Couldn't find suitable encoder"))?,
-    )?;
-    let mut encoder = stream.codec().encoder().video()?;
-    encoder.set_flags(codec::Flags::GLOBAL_HEADER);
-    encoder.set_width(dst_w);
-    encoder.set_height(dst_h);
-    encoder.set_frame_rate(Some(enc_tb.invert()));
-    encoder.set_format(YUV420P);
-    encoder.set_time_base(enc_tb);
-    stream.set_parameters(
-        encoder.open_with([("crf", "18"), ("preset", "veryslow")].iter().collect())?,
-    );
-    stream.set_time_base(OUTPUT_TIME_BASE);
-    let encoder = &mut stream.codec().encoder().video()?;
-    output.write_header()?;
-    let packet = &mut Packet::empty();
-    send_frame(encoder, decoded, scaler, scaled)?;
-    receive_packet(encoder, output, packet, enc_tb)?;
-    for Frame { filename, delay } in frames {
-        send_packet(
-            decoder,
-            &mut archive.by_name(filename.as_ref())?,
-            ts,
-            *delay as i64,
-            enc_tb,
-        )?;
-        while wrap_result(decoder.receive_frame(decoded))? {
-            send_frame(encoder, decoded, scaler, scaled)?;
-            receive_packet(encoder, output, packet, enc_tb)?;
+    let is_cancelled = || {
+        options
+            .cancellation_token
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    };
+    let mut cancelled = is_cancelled();
+    for (index, (frame, delay)) in frames.iter().zip(delays.iter().copied()).enumerate() {
+        if cancelled {
+            break;
+        }
+        if let Some(mut video) = source.read_decoded(frame.filename.as_ref())? {
+            // The slides still in the decoder come first, and it can't take more after that.
+            if !pending.is_empty() {
+                flush_decoder(decoder, decoded, pending, renditions)?;
+                *decoder = open_decoder_with(decoder_kind, options)?;
+            }
+            let pts = ts.rescale(options.decoder_time_base, enc_tb);
+            *ts += (delay as i64).rescale(Rational(1, MILLIS), options.decoder_time_base);
+            video.set_pts(Some(pts));
+            // `timestamp` reads the decoder's estimate, which nothing else fills in.
+            unsafe { (*video.as_mut_ptr()).best_effort_timestamp = pts };
+            renditions.send(
+                &mut video,
+                PendingSlide::new(frame, Duration::ZERO),
+                Duration::ZERO,
+            )?;
+            cancelled = is_cancelled();
+            continue;
+        }
+        let id = input_codec(frame.filename.as_ref());
+        if id != decoder_kind {
+            // Slides still buffered in the old decoder have to come out before it's replaced.
+            flush_decoder(decoder, decoded, pending, renditions)?;
+            *decoder = open_decoder_with(id, options)?;
+            decoder_kind = id;
         }
+        let data = source.read_frame(frame.filename.as_ref())?;
+        let mut decoding =
+            send_packet(decoder, &data, ts, delay, options.decoder_time_base, enc_tb)
+                .map(|elapsed| pending.push_back(PendingSlide::new(frame, elapsed)));
+        // Threaded decoders hold on to several slides before the first comes out.
+        while decoding.is_ok() {
+            match receive_frame(decoder, decoded) {
+                Ok(Some(elapsed)) => {
+                    renditions.send(decoded, pending.pop_front().unwrap_or_default(), elapsed)?
+                }
+                Ok(None) => break,
+                Err(err) => decoding = Err(err),
+            }
+        }
+        if let Err(err) = decoding {
+            if !options.skip_bad_leading_slides || !renditions.chains.is_empty() {
+                return Err(err);
+            }
+            // The decoder may be left in any state, and whatever it still held goes with it, so
+            // the video starts over with the next slide.
+            *ts = 0;
+            pending.clear();
+            renditions.first = index + 1;
+            *decoder = open_decoder_with(decoder_kind, options)?;
+        }
+        cancelled = is_cancelled();
+    }
+    flush_decoder(decoder, decoded, pending, renditions)?;
+    if renditions.chains.is_empty() {
+        return Err(if cancelled {
+            anyhow::Error::new(SlidevidError::Cancelled).context(error::NothingWritten)
+        } else {
+            anyhow!("The decoder didn't return any of the slides")
+        });
+    }
+    // Players disagree on whether the last frame's duration counts, so the last slide is
+    // repeated one tick before the end to pin down where the video stops. Slides shorter than
+    // a tick are rounded to the nearest one, which can leave no tick free for the repeat.
+    let end = ts.rescale(options.decoder_time_base, enc_tb);
+    let hold_pts = (!cancelled && options.end_hold_ms.is_some_and(|hold| hold > 0))
+        .then(|| end - 1)
+        .filter(|&pts| pts > renditions.last_pts);
+    let timing_mode = timing::timing_mode(&delays[renditions.first..], options);
+    let mut stats = Vec::with_capacity(renditions.chains.len());
+    for chain in renditions.chains.drain(..) {
+        let (codec_params, keyframes) = chain.finish(hold_pts, end, enc_tb)?;
+        stats.push(EncodeStats {
+            output_path: PathBuf::new(),
+            codec_params,
+            frame_timings: renditions.frame_timings.clone(),
+            slide_sizes: renditions.slide_sizes.clone(),
+            keyframes,
+            timing_mode,
+            spec: None,
+        });
+    }
+    if cancelled {
+        return Err(SlidevidError::Cancelled.into());
     }
+    Ok(stats)
+}
+
+/// Sends the slides the decoder still holds on to the outputs.
+fn flush_decoder<S: AsRef<str>>(
+    decoder: &mut decoder::Opened,
+    decoded: &mut frame::Video,
+    pending: &mut VecDeque<PendingSlide>,
+    renditions: &mut Renditions<S>,
+) -> Result<()> {
     decoder.send_eof()?;
-    while wrap_result(decoder.receive_frame(decoded))? {
-        send_frame(encoder, decoded, scaler, scaled)?;
-        receive_packet(encoder, output, packet, enc_tb)?;
+    while let Some(elapsed) = receive_frame(decoder, decoded)? {
+        renditions.send(decoded, pending.pop_front().unwrap_or_default(), elapsed)?;
+    }
+    Ok(())
+}
+
+/// The outputs fed by [`encode_renditions`], whose chains are created once the first slide is
+/// decoded and transformed, since they take its size.
+struct Renditions<'a, 'o, S: AsRef<str>> {
+    outputs: Vec<(&'o mut format::context::Output, Option<u32>)>,
+    chains: Vec<Chain<'o>>,
+    frames: &'a [Frame<S>],
+    delays: &'a [u32],
+    /// The first slide of the video, after any bad leading slides that were skipped.
+    first: usize,
+    transformer: transform::Transformer,
+    frame_timings: Vec<FrameTiming>,
+    slide_sizes: Vec<(u32, u32)>,
+    /// Timestamp of the last slide sent, in `time_base`.
+    last_pts: i64,
+    options: &'a ConvertOptions,
+    pass: Pass<'a>,
+    time_base: Rational,
+}
+
+impl<S: AsRef<str>> Renditions<'_, '_, S> {
+    /// Transforms a slide that took `receive_frame` to decode and sends it to every output.
+    fn send(
+        &mut self,
+        decoded: &mut frame::Video,
+        mut slide: PendingSlide,
+        receive_frame: Duration,
+    ) -> Result<()> {
+        slide.timing.receive_frame = receive_frame;
+        self.slide_sizes.push((decoded.width(), decoded.height()));
+        self.last_pts = decoded.timestamp().unwrap_or_default();
+        let transformed = self.transformer.apply(decoded, &slide)?;
+        if self.chains.is_empty() {
+            let (frames, delays) = (&self.frames[self.first..], &self.delays[self.first..]);
+            for (output, height) in self.outputs.drain(..) {
+                // Chapters go in the header, and only once it's known which slides are skipped.
+                chapters::add_chapters(output, frames, delays, self.options.chapters)?;
+                let chain = Chain::new(
+                    output,
+                    transformed,
+                    height,
+                    self.options,
+                    self.pass,
+                    self.time_base,
+                )?;
+                self.chains.push(chain);
+            }
+        }
+        send_to_all(&mut self.chains, transformed, &mut slide, self.time_base)?;
+        if self.options.benchmark {
+            self.frame_timings.push(slide.timing);
+        }
+        Ok(())
+    }
+}
+
+fn send_to_all(
+    chains: &mut [Chain],
+    transformed: &frame::Video,
+    slide: &mut PendingSlide,
+    time_base: Rational,
+) -> Result<()> {
+    for chain in chains {
+        chain.send(
+            transformed,
+            slide.metadata,
+            slide.scaling,
+            &mut slide.timing,
+            time_base,
+        )?;
     }
-    encoder.send_eof()?;
-    receive_packet(encoder, output, packet, enc_tb)?;
-    output.write_trailer()?;
     Ok(())
 }
+
+/// A slide sent to the decoder, with what's needed to encode it once it's decoded.
+#[derive(Default)]
+struct PendingSlide<'a> {
+    metadata: Option<&'a [u8]>,
+    subtitle: Option<&'a str>,
+    scaling: Option<ScaleAlgorithm>,
+    size: Option<(u32, u32)>,
+    transform: Option<Transform>,
+    timing: FrameTiming,
+}
+
+impl<'a> PendingSlide<'a> {
+    fn new<S: AsRef<str>>(frame: &'a Frame<S>, send_packet: Duration) -> Self {
+        Self {
+            metadata: frame.metadata.as_deref(),
+            subtitle: frame.subtitle.as_deref(),
+            scaling: frame.scaling,
+            size: frame.size,
+            transform: frame.transform,
+            timing: FrameTiming {
+                send_packet,
+                ..FrameTiming::default()
+            },
+        }
+    }
+}
+
+/// The scaler and encoder feeding one output.
+struct Chain<'a> {
+    output: &'a mut format::context::Output,
+    encoder: encoder::video::Video,
+    scaler: scaling::Context,
+    scaling: ScaleAlgorithm,
+    scaled: Scaled,
+    packet: Packet,
+    codec_params: codec::Parameters,
+    output_time_base: Rational,
+    /// Timestamps of the keyframes written so far, in the encoder's time base.
+    keyframes: Vec<i64>,
+    audio: Option<audio::SilentTrack>,
+    /// The conversion's options with the colors they leave to the first slide filled in.
+    options: ConvertOptions,
+}
+
+impl<'a> Chain<'a> {
+    fn new(
+        output: &'a mut format::context::Output,
+        first: &frame::Video,
+        height: Option<u32>,
+        options: &ConvertOptions,
+        pass: Pass,
+        time_base: Rational,
+    ) -> Result<Self> {
+        let options = color::resolve(options, first).into_owned();
+        let options = &options;
+        let (scaler, scaled) = create_scaler(first, options, height)?;
+        let (dst_w, dst_h) = (scaled.width(), scaled.height());
+        let mut stream = output.add_stream(find_encoder(options)?)?;
+        let mut encoder = stream.codec().encoder().video()?;
+        let encoder_options =
+            configure_encoder(&mut encoder, options, pass, (dst_w, dst_h), time_base)?;
+        stream.set_parameters(encoder.open_with(encoder_options)?);
+        if let Some(sar) = options.sample_aspect_ratio {
+            // Muxers read the stream's aspect ratio rather than the codec parameters' one.
+            unsafe { (*stream.as_mut_ptr()).sample_aspect_ratio = sar.into() };
+        }
+        stream.set_time_base(options.output_time_base);
+        let stream_index = stream.index();
+        let encoder = stream.codec().encoder().video()?;
+        // A first pass only collects the video's stats.
+        let audio = (options.silent_audio && !matches!(pass, Pass::First(_)))
+            .then(|| audio::SilentTrack::new(output, options))
+            .transpose()?;
+        let mut muxer_options = Dictionary::new();
+        // The null muxer of a first pass doesn't know any of the real muxer's options.
+        if !matches!(pass, Pass::First(_)) {
+            for &(key, value) in options.container.muxer_options() {
+                muxer_options.set(key, value);
+            }
+            for (key, value) in &options.muxer_options {
+                muxer_options.set(key, value);
+            }
+        }
+        let unused = output.write_header_with(muxer_options)?;
+        if let Some((key, _)) = unused.iter().next() {
+            return Err(anyhow!("Unknown muxer option {:?}", key));
+        }
+        // Muxers that only support particular time bases replace the requested one.
+        let stream = output.stream(stream_index).unwrap();
+        let (codec_params, output_time_base) = (stream.parameters().clone(), stream.time_base());
+        Ok(Self {
+            output,
+            encoder,
+            scaler,
+            scaling: ScaleAlgorithm::default(),
+            scaled: Scaled::new(scaled),
+            packet: Packet::empty(),
+            codec_params,
+            output_time_base,
+            keyframes: Vec::new(),
+            audio,
+            options: options.clone(),
+        })
+    }
+
+    fn send(
+        &mut self,
+        slide: &frame::Video,
+        metadata: Option<&[u8]>,
+        scaling: Option<ScaleAlgorithm>,
+        timing: &mut FrameTiming,
+        time_base: Rational,
+    ) -> Result<()> {
+        // Frames without an override go back to the default algorithm. Size changes are handled
+        // here as well, since `send_frame` would rebuild the scaler with the default.
+        let scaling = scaling.unwrap_or_default();
+        let src = self.scaler.input();
+        let (src_format, src_w, src_h) = (slide.format(), slide.width(), slide.height());
+        if scaling != self.scaling
+            || (src_format, src_w, src_h) != (src.format, src.width, src.height)
+        {
+            let dst = *self.scaler.output();
+            self.scaler.cached(
+                src_format,
+                src_w,
+                src_h,
+                dst.format,
+                dst.width,
+                dst.height,
+                scaling.flags(),
+            );
+            color::configure_scaler(&mut self.scaler, &self.options);
+            self.scaling = scaling;
+        }
+        if let Some(audio) = &mut self.audio {
+            audio.fill(self.output, slide.timestamp().unwrap_or(0), time_base)?;
+        }
+        send_frame(
+            &mut self.encoder,
+            slide,
+            &mut self.scaler,
+            &mut self.scaled,
+            metadata,
+            &self.options,
+            timing,
+        )?;
+        receive_packet(
+            &mut self.encoder,
+            self.output,
+            &mut self.packet,
+            time_base,
+            self.output_time_base,
+            &mut self.keyframes,
+        )
+    }
+
+    /// Flushes the encoder, first repeating the last slide at `hold_pts` if given, ends any
+    /// silent track at `end` and finishes the output, returning its parameters and when each of
+    /// the slides' keyframes is shown.
+    fn finish(
+        mut self,
+        hold_pts: Option<i64>,
+        end: i64,
+        time_base: Rational,
+    ) -> Result<(codec::Parameters, Vec<Duration>)> {
+        if let Some(pts) = hold_pts {
+            let mut paletted = None;
+            let frame = match self.encoder.format() {
+                format::Pixel::PAL8 => paletted.insert(palette::quantize(&self.scaled.frame)?),
+                _ => &mut self.scaled.frame,
+            };
+            frame.set_pts(Some(pts));
+            frame.set_kind(picture::Type::None);
+            sei::attach(frame, None)?;
+            self.encoder.send_frame(frame)?;
+            receive_packet(
+                &mut self.encoder,
+                self.output,
+                &mut self.packet,
+                time_base,
+                self.output_time_base,
+                &mut self.keyframes,
+            )?;
+        }
+        self.encoder.send_eof()?;
+        receive_packet(
+            &mut self.encoder,
+            self.output,
+            &mut self.packet,
+            time_base,
+            self.output_time_base,
+            &mut self.keyframes,
+        )?;
+        if let Some(audio) = self.audio.take() {
+            audio.finish(self.output, end, time_base)?;
+        }
+        self.output.write_trailer()?;
+        let keyframes = self
+            .keyframes
+            .iter()
+            .filter(|&&pts| Some(pts) != hold_pts)
+            .map(|pts| Duration::from_micros(pts.rescale(time_base, Rational(1, 1_000_000)) as u64))
+            .collect();
+        Ok((self.codec_params, keyframes))
+    }
+}
@@ -0,0 +1,623 @@
+use crate::{
+    BitDepth, CancellationToken, ColorFilter, ColorPrimaries, ColorRange, ColorSpace, ColorTrc,
+    Container, H265Profile, LetterboxFill, Level, PixelFormat, Profile, RateControl, Rect, Rgb,
+    SubtitleStyle, Transform, WriteMode, DECODER_TIME_BASE, OUTPUT_TIME_BASE,
+};
+use ffmpeg::{codec, Rational};
+#[cfg(feature = "report")]
+use std::path::PathBuf;
+use std::{borrow::Cow, num::NonZeroU8, ops::Range, path::Path};
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ConvertOptions {
+    pub container: Container,
+    /// Pick the container from the output path's extension when `container` doesn't match it.
+    /// Setting a container with [`ConvertOptions::container`] turns this off.
+    pub detect_container: bool,
+    /// Codec to encode with instead of the container's own, such as `RAWVIDEO` for
+    /// uncompressed frames to pipe into another process.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub codec: Option<codec::Id>,
+    /// Encoder to use by name, such as `h264_videotoolbox`, when FFmpeg has it and it encodes
+    /// the right codec. Otherwise the codec's default encoder is used.
+    pub preferred_encoder: Option<String>,
+    pub write_mode: WriteMode,
+    pub atomic: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cancellation_token: Option<CancellationToken>,
+    pub min_slide_ms: Option<u32>,
+    pub max_slide_ms: Option<u32>,
+    /// Delay of frames created with [`Frame::without_delay`](crate::Frame::without_delay).
+    pub default_delay_ms: Option<u32>,
+    pub speed_factor: f64,
+    /// Highest frame rate to output, with slides shorter than a frame at this rate lengthened
+    /// at the expense of the slides after them.
+    pub max_fps: Option<u32>,
+    /// Extra time to linger on the last slide, on top of its own delay.
+    pub end_hold_ms: Option<u32>,
+    /// Indices of the frames to encode, with the first of them starting the video at time 0.
+    pub frame_range: Option<Range<usize>>,
+    /// Start a chapter at every slide rather than only at frames with a chapter title.
+    pub chapters: bool,
+    /// Let [`concat_videos`](crate::concat_videos) scale segments to the first one's size.
+    pub rescale_segments: bool,
+    /// Fail before encoding a zip archive given to [`convert`](crate::convert) if its slides
+    /// aren't all the same size.
+    pub reject_mixed_resolution: bool,
+    /// Options passed to the muxer, such as `movflags`, applied in order.
+    pub muxer_options: Vec<(String, String)>,
+    /// Add a silent stereo AAC track as long as the video, for players that reject video-only
+    /// files. MP4, MOV and MKV can hold it. [`SlideEncoder`](crate::SlideEncoder) only returns
+    /// video packets and ignores it.
+    pub silent_audio: bool,
+    /// Maximum number of frames between keyframes. Shorter intervals make seeking more
+    /// precise at the cost of a larger file; `None` keeps the encoder's default.
+    pub gop_size: Option<u32>,
+    /// Maximum number of consecutive B-frames, with 0 disabling them for decoders that can't
+    /// handle them; `None` keeps the encoder's default.
+    pub bframes: Option<u32>,
+    /// Force a keyframe at the start of every slide, so players can seek to any slide exactly.
+    pub keyframe_per_slide: bool,
+    pub rate_control: RateControl,
+    /// Peak bitrate of a [`RateControl::Crf`] encode, in bits per second, which otherwise has no
+    /// cap on how large busy slides get.
+    pub max_bitrate: Option<usize>,
+    pub profile: Option<Profile>,
+    /// Profile for HEVC outputs, ignored by other codecs.
+    pub h265_profile: Option<H265Profile>,
+    /// H.264 level; `None` picks the lowest level that fits the output resolution and frame
+    /// rate, so 4K and 8K decks aren't capped by the encoder's default.
+    pub level: Option<Level>,
+    /// Matrix to convert slides to YUV with; `None` keeps the first slide's, or BT.709 for RGB
+    /// slides.
+    pub colorspace: Option<ColorSpace>,
+    /// `None` keeps the first slide's range, or full range for RGB slides.
+    pub color_range: Option<ColorRange>,
+    /// `None` keeps the first slide's primaries where it has any, or else tags BT.709.
+    pub color_primaries: Option<ColorPrimaries>,
+    /// Transfer characteristic to tag the output with; `None` keeps the first slide's where it
+    /// has one, or else uses the colorspace's own.
+    pub color_trc: Option<ColorTrc>,
+    pub pixel_format: PixelFormat,
+    pub benchmark: bool,
+    /// Encode and mux as usual but write to the null device, to measure the pipeline alone.
+    pub discard_output: bool,
+    /// Validate the slides and settings and report the planned output without encoding.
+    pub dry_run: bool,
+    /// Region of each decoded slide to keep, in source pixels, applied before any transform.
+    pub crop: Option<Rect>,
+    /// Exact width and height of the output, with every slide scaled to fit and centered over
+    /// [`letterbox_fill`](Self::letterbox_fill); `None` takes the first slide's size.
+    pub output_size: Option<(u32, u32)>,
+    pub transform: Option<Transform>,
+    /// Turn JPEG slides upright according to their EXIF orientation before any transform.
+    pub auto_orient: bool,
+    /// Fill for the bars around slides fitted into [`output_size`](Self::output_size) or, with
+    /// their own transform, into the first slide's size.
+    pub letterbox_fill: LetterboxFill,
+    pub filter: Option<ColorFilter>,
+    /// Strength of an unsharp mask applied to the luma of each scaled slide, to crisp up text
+    /// softened by downscaling. Around 0.5 to 1.5 is typical; conversions fail on negative or
+    /// non-finite strengths.
+    pub sharpen: Option<f32>,
+    /// Background that slides with an alpha channel are composited onto, white by default.
+    pub background_color: Rgb,
+    /// Decoder to use for every slide instead of picking one from the file extension.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub force_input_codec: Option<codec::Id>,
+    /// Drop slides that fail to decode before the first one that doesn't, instead of failing,
+    /// with the slides after them moving up to start the video.
+    pub skip_bad_leading_slides: bool,
+    /// Number of frame threads to decode slides with, several slides at a time.
+    pub decoder_threads: Option<NonZeroU8>,
+    /// Largest slide, decompressed, to accept from a zip archive.
+    pub max_frame_bytes: Option<u64>,
+    /// Find frames missing from zip archives under extra leading directories, or failing that
+    /// by their basename, as long as only one entry matches.
+    pub loose_entry_names: bool,
+    /// Password to decrypt zip archives with.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub password: Option<Vec<u8>>,
+    pub subtitle_style: SubtitleStyle,
+    /// Burn frames' subtitles into the video, rather than only into the subtitle files
+    /// generated from them.
+    pub burn_subtitles: bool,
+    /// Shape of a pixel for players to stretch to; `None` keeps square pixels.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub sample_aspect_ratio: Option<Rational>,
+    /// Time base slides are timestamped in before decoding, which must represent every slide
+    /// duration exactly.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub decoder_time_base: Rational,
+    /// Time base requested for the output stream, which the muxer may override.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub output_time_base: Rational,
+    /// Where to write an [`EncodeReport`](crate::EncodeReport) as JSON after converting to a file.
+    #[cfg(feature = "report")]
+    pub report_path: Option<PathBuf>,
+    /// Write the report for failed and cancelled conversions too.
+    #[cfg(feature = "report")]
+    pub partial_report: bool,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            container: Container::default(),
+            detect_container: true,
+            codec: None,
+            preferred_encoder: None,
+            write_mode: WriteMode::default(),
+            atomic: true,
+            cancellation_token: None,
+            min_slide_ms: None,
+            max_slide_ms: None,
+            default_delay_ms: None,
+            speed_factor: 1.0,
+            max_fps: None,
+            end_hold_ms: None,
+            frame_range: None,
+            chapters: false,
+            rescale_segments: false,
+            reject_mixed_resolution: false,
+            muxer_options: Vec::new(),
+            silent_audio: false,
+            gop_size: None,
+            bframes: None,
+            keyframe_per_slide: false,
+            rate_control: RateControl::default(),
+            max_bitrate: None,
+            profile: None,
+            h265_profile: None,
+            level: None,
+            colorspace: None,
+            color_range: None,
+            color_primaries: None,
+            color_trc: None,
+            pixel_format: PixelFormat::default(),
+            benchmark: false,
+            discard_output: false,
+            dry_run: false,
+            crop: None,
+            output_size: None,
+            transform: None,
+            auto_orient: true,
+            letterbox_fill: LetterboxFill::default(),
+            filter: None,
+            sharpen: None,
+            background_color: Rgb::default(),
+            force_input_codec: None,
+            skip_bad_leading_slides: false,
+            decoder_threads: None,
+            max_frame_bytes: None,
+            loose_entry_names: false,
+            password: None,
+            subtitle_style: SubtitleStyle::default(),
+            burn_subtitles: true,
+            sample_aspect_ratio: None,
+            decoder_time_base: DECODER_TIME_BASE,
+            output_time_base: OUTPUT_TIME_BASE,
+            #[cfg(feature = "report")]
+            report_path: None,
+            #[cfg(feature = "report")]
+            partial_report: false,
+        }
+    }
+}
+
+impl ConvertOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn container(mut self, container: Container) -> Self {
+        self.container = container;
+        self.detect_container = false;
+        self
+    }
+
+    pub fn detect_container(mut self, detect_container: bool) -> Self {
+        self.detect_container = detect_container;
+        self
+    }
+
+    pub fn codec(mut self, id: codec::Id) -> Self {
+        self.codec = Some(id);
+        self
+    }
+
+    pub fn preferred_encoder(mut self, name: impl Into<String>) -> Self {
+        self.preferred_encoder = Some(name.into());
+        self
+    }
+
+    /// Settings for QuickTime and iMovie: a MOV with High profile level 4.0 H.264 from
+    /// VideoToolbox where available, `moov` up front, limited range sRGB color and a silent
+    /// stereo AAC track, which some QuickTime and iOS paths need to open the file at all.
+    pub fn macos_compatible(self) -> Self {
+        self.container(Container::Mov)
+            .codec(codec::Id::H264)
+            .preferred_encoder("h264_videotoolbox")
+            .profile(Profile::High)
+            .level(Level::L40)
+            .pixel_format(PixelFormat::Yuv420p)
+            .color_range(ColorRange::Tv)
+            .color_primaries(ColorPrimaries::Bt709)
+            .color_trc(ColorTrc::Iec61966_2_1)
+            .muxer_option("movflags", "+faststart")
+            .silent_audio(true)
+    }
+
+    /// Settings for an MP4 that plays in Chrome 4+, Firefox 35+, Safari 3.1+ and Edge 12+: High
+    /// profile level 4.0 H.264 in 8-bit 4:2:0, limited range BT.709, `moov` up front, at most
+    /// 8 Mbps and a silent stereo AAC track.
+    pub fn web_compatible(self) -> Self {
+        self.container(Container::Mp4)
+            .codec(codec::Id::H264)
+            .profile(Profile::High)
+            .level(Level::L40)
+            .pixel_format(PixelFormat::Yuv420p)
+            .colorspace(ColorSpace::Bt709)
+            .color_range(ColorRange::Tv)
+            .color_primaries(ColorPrimaries::Bt709)
+            .color_trc(ColorTrc::Bt709)
+            .max_bitrate(8_000_000)
+            .muxer_option("movflags", "+faststart")
+            .silent_audio(true)
+    }
+
+    /// The codec to encode with, [`codec`](Self::codec) if set or else the container's.
+    pub(crate) fn codec_id(&self) -> codec::Id {
+        self.codec.unwrap_or_else(|| self.container.codec())
+    }
+
+    /// These options with the container detected from `path`, if that's enabled and needed.
+    pub(crate) fn for_output(&self, path: &Path) -> Cow<'_, ConvertOptions> {
+        let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        let matches = self
+            .container
+            .extensions()
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case(ext));
+        match Container::from_extension(ext) {
+            Some(container) if self.detect_container && !matches => Cow::Owned(ConvertOptions {
+                container,
+                ..self.clone()
+            }),
+            _ => Cow::Borrowed(self),
+        }
+    }
+
+    pub fn write_mode(mut self, write_mode: WriteMode) -> Self {
+        self.write_mode = write_mode;
+        self
+    }
+
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    pub fn min_slide_ms(mut self, min_slide_ms: u32) -> Self {
+        self.min_slide_ms = Some(min_slide_ms);
+        self
+    }
+
+    pub fn max_slide_ms(mut self, max_slide_ms: u32) -> Self {
+        self.max_slide_ms = Some(max_slide_ms);
+        self
+    }
+
+    pub fn default_delay_ms(mut self, default_delay_ms: u32) -> Self {
+        self.default_delay_ms = Some(default_delay_ms);
+        self
+    }
+
+    pub fn end_hold_ms(mut self, end_hold_ms: u32) -> Self {
+        self.end_hold_ms = Some(end_hold_ms);
+        self
+    }
+
+    pub fn frame_range(mut self, start: usize, end: usize) -> Self {
+        self.frame_range = Some(start..end);
+        self
+    }
+
+    pub fn chapters(mut self, chapters: bool) -> Self {
+        self.chapters = chapters;
+        self
+    }
+
+    pub fn rescale_segments(mut self, rescale_segments: bool) -> Self {
+        self.rescale_segments = rescale_segments;
+        self
+    }
+
+    pub fn reject_mixed_resolution(mut self, reject_mixed_resolution: bool) -> Self {
+        self.reject_mixed_resolution = reject_mixed_resolution;
+        self
+    }
+
+    pub fn muxer_option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.muxer_options.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn silent_audio(mut self, silent_audio: bool) -> Self {
+        self.silent_audio = silent_audio;
+        self
+    }
+
+    pub fn speed_factor(mut self, speed_factor: f64) -> Self {
+        self.speed_factor = speed_factor;
+        self
+    }
+
+    pub fn max_fps(mut self, max_fps: u32) -> Self {
+        self.max_fps = Some(max_fps);
+        self
+    }
+
+    pub fn gop_size(mut self, gop_size: u32) -> Self {
+        self.gop_size = Some(gop_size);
+        self
+    }
+
+    pub fn bframes(mut self, bframes: u32) -> Self {
+        self.bframes = Some(bframes);
+        self
+    }
+
+    pub fn keyframe_per_slide(mut self, keyframe_per_slide: bool) -> Self {
+        self.keyframe_per_slide = keyframe_per_slide;
+        self
+    }
+
+    pub fn rate_control(mut self, rate_control: RateControl) -> Self {
+        self.rate_control = rate_control;
+        self
+    }
+
+    pub fn max_bitrate(mut self, max_bitrate: usize) -> Self {
+        self.max_bitrate = Some(max_bitrate);
+        self
+    }
+
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    pub fn h265_profile(mut self, h265_profile: H265Profile) -> Self {
+        self.h265_profile = Some(h265_profile);
+        self
+    }
+
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    pub fn colorspace(mut self, colorspace: ColorSpace) -> Self {
+        self.colorspace = Some(colorspace);
+        self
+    }
+
+    pub fn color_range(mut self, color_range: ColorRange) -> Self {
+        self.color_range = Some(color_range);
+        self
+    }
+
+    pub fn color_primaries(mut self, color_primaries: ColorPrimaries) -> Self {
+        self.color_primaries = Some(color_primaries);
+        self
+    }
+
+    pub fn color_trc(mut self, color_trc: ColorTrc) -> Self {
+        self.color_trc = Some(color_trc);
+        self
+    }
+
+    pub fn pixel_format(mut self, pixel_format: PixelFormat) -> Self {
+        self.pixel_format = pixel_format;
+        self
+    }
+
+    /// Switches [`pixel_format`](Self::pixel_format) to `bit_depth`, keeping its chroma
+    /// subsampling.
+    pub fn bit_depth(mut self, bit_depth: BitDepth) -> Self {
+        self.pixel_format = self.pixel_format.with_bit_depth(bit_depth);
+        self
+    }
+
+    pub fn benchmark(mut self, benchmark: bool) -> Self {
+        self.benchmark = benchmark;
+        self
+    }
+
+    pub fn discard_output(mut self, discard_output: bool) -> Self {
+        self.discard_output = discard_output;
+        self
+    }
+
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn crop(mut self, crop: Rect) -> Self {
+        self.crop = Some(crop);
+        self
+    }
+
+    pub fn output_size(mut self, width: u32, height: u32) -> Self {
+        self.output_size = Some((width, height));
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+
+    pub fn auto_orient(mut self, auto_orient: bool) -> Self {
+        self.auto_orient = auto_orient;
+        self
+    }
+
+    pub fn letterbox_fill(mut self, letterbox_fill: LetterboxFill) -> Self {
+        self.letterbox_fill = letterbox_fill;
+        self
+    }
+
+    pub fn filter(mut self, filter: ColorFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn sharpen(mut self, strength: f32) -> Self {
+        self.sharpen = Some(strength);
+        self
+    }
+
+    pub fn background_color(mut self, background_color: Rgb) -> Self {
+        self.background_color = background_color;
+        self
+    }
+
+    pub fn force_input_codec(mut self, id: codec::Id) -> Self {
+        self.force_input_codec = Some(id);
+        self
+    }
+
+    pub fn skip_bad_leading_slides(mut self, skip_bad_leading_slides: bool) -> Self {
+        self.skip_bad_leading_slides = skip_bad_leading_slides;
+        self
+    }
+
+    pub fn decoder_threads(mut self, decoder_threads: NonZeroU8) -> Self {
+        self.decoder_threads = Some(decoder_threads);
+        self
+    }
+
+    pub fn max_frame_bytes(mut self, max_frame_bytes: u64) -> Self {
+        self.max_frame_bytes = Some(max_frame_bytes);
+        self
+    }
+
+    pub fn loose_entry_names(mut self, loose_entry_names: bool) -> Self {
+        self.loose_entry_names = loose_entry_names;
+        self
+    }
+
+    pub fn password<P: Into<Vec<u8>>>(mut self, password: P) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    pub fn subtitle_style(mut self, subtitle_style: SubtitleStyle) -> Self {
+        self.subtitle_style = subtitle_style;
+        self
+    }
+
+    pub fn burn_subtitles(mut self, burn_subtitles: bool) -> Self {
+        self.burn_subtitles = burn_subtitles;
+        self
+    }
+
+    pub fn sample_aspect_ratio<R: Into<Rational>>(mut self, sample_aspect_ratio: R) -> Self {
+        self.sample_aspect_ratio = Some(sample_aspect_ratio.into());
+        self
+    }
+
+    pub fn decoder_time_base<R: Into<Rational>>(mut self, decoder_time_base: R) -> Self {
+        self.decoder_time_base = decoder_time_base.into();
+        self
+    }
+
+    pub fn output_time_base<R: Into<Rational>>(mut self, output_time_base: R) -> Self {
+        self.output_time_base = output_time_base.into();
+        self
+    }
+
+    #[cfg(feature = "report")]
+    pub fn report_path(mut self, report_path: impl Into<PathBuf>) -> Self {
+        self.report_path = Some(report_path.into());
+        self
+    }
+
+    #[cfg(feature = "report")]
+    pub fn partial_report(mut self, partial_report: bool) -> Self {
+        self.partial_report = partial_report;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setters_override_the_defaults() {
+        let options = ConvertOptions::new()
+            .codec(codec::Id::HEVC)
+            .write_mode(WriteMode::FailIfExists)
+            .atomic(false)
+            .min_slide_ms(50)
+            .max_slide_ms(5_000)
+            .frame_range(1, 3)
+            .gop_size(12)
+            .pixel_format(PixelFormat::Yuv444p);
+        assert_eq!(options.codec_id(), codec::Id::HEVC);
+        assert_eq!(options.write_mode, WriteMode::FailIfExists);
+        assert!(!options.atomic);
+        assert_eq!(
+            (options.min_slide_ms, options.max_slide_ms),
+            (Some(50), Some(5_000))
+        );
+        assert_eq!(options.frame_range, Some(1..3));
+        assert_eq!(options.gop_size, Some(12));
+        assert_eq!(options.pixel_format, PixelFormat::Yuv444p);
+        assert_eq!(options.rate_control, RateControl::Crf(crate::DEFAULT_CRF));
+    }
+
+    #[test]
+    fn an_explicit_container_stops_detection() {
+        let detected = ConvertOptions::new();
+        assert_eq!(
+            detected.for_output(Path::new("out.mkv")).container,
+            Container::Mkv
+        );
+        let explicit = ConvertOptions::new().container(Container::Mp4);
+        assert_eq!(
+            explicit.for_output(Path::new("out.mkv")).container,
+            Container::Mp4
+        );
+    }
+
+    #[test]
+    fn presets_target_h264_with_moov_up_front() {
+        for options in [
+            ConvertOptions::new().macos_compatible(),
+            ConvertOptions::new().web_compatible(),
+        ]
+        .iter()
+        {
+            assert_eq!(options.codec_id(), codec::Id::H264);
+            assert_eq!(options.profile, Some(Profile::High));
+            assert_eq!(options.level, Some(Level::L40));
+            assert_eq!(options.color_range, Some(ColorRange::Tv));
+            assert!(options.silent_audio);
+            assert!(options
+                .muxer_options
+                .contains(&("movflags".to_owned(), "+faststart".to_owned())));
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::{anyhow, Result};
+use std::{
+    fs::{self, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WriteMode {
+    #[default]
+    Overwrite,
+    FailIfExists,
+    AppendTimestamp,
+}
+
+/// The path to write an output to, which for the modes that mustn't clobber an existing file
+/// is claimed by an empty placeholder until the output is written over it. Placeholders that
+/// are never kept are removed on drop.
+pub(crate) struct OutputPath {
+    path: PathBuf,
+    placeholder: bool,
+}
+
+impl OutputPath {
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Leaves the file at the path in place, once the output has been written to it.
+    pub(crate) fn keep(&mut self) {
+        self.placeholder = false;
+    }
+}
+
+impl Drop for OutputPath {
+    fn drop(&mut self) {
+        if self.placeholder {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+pub(crate) fn resolve_output_path(path: &Path, mode: WriteMode) -> Result<OutputPath> {
+    match mode {
+        WriteMode::Overwrite => Ok(OutputPath {
+            path: path.to_owned(),
+            placeholder: false,
+        }),
+        WriteMode::FailIfExists => match claim(path) {
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                Err(anyhow!("Output file {:?} already exists", path))
+            }
+            result => Ok(result?),
+        },
+        WriteMode::AppendTimestamp => {
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            let ext = path.extension().and_then(|e| e.to_str());
+            // Conversions starting in the same millisecond count up from there.
+            let mut attempt = 0;
+            loop {
+                let mut name = format!("{}_{}", stem, timestamp);
+                if attempt > 0 {
+                    name.push_str(&format!("_{}", attempt));
+                }
+                if let Some(ext) = ext {
+                    name.push('.');
+                    name.push_str(ext);
+                }
+                match claim(&path.with_file_name(name)) {
+                    Err(err) if err.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+                    result => return Ok(result?),
+                }
+            }
+        }
+    }
+}
+
+/// Creates an empty placeholder at `path`, failing if a file is already there.
+fn claim(path: &Path) -> io::Result<OutputPath> {
+    OpenOptions::new().write(true).create_new(true).open(path)?;
+    Ok(OutputPath {
+        path: path.to_owned(),
+        placeholder: true,
+    })
+}
+
+pub(crate) struct TempOutput {
+    temp: PathBuf,
+    target: PathBuf,
+    committed: bool,
+}
+
+impl TempOutput {
+    pub fn new(target: &Path) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        // The target's stem keeps renditions written side by side from sharing a temp file.
+        let stem = target.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let mut name = format!(".slidevid_tmp_{}{:09}_{}", process::id(), nanos, stem);
+        if let Some(ext) = target.extension().and_then(|e| e.to_str()) {
+            name.push('.');
+            name.push_str(ext);
+        }
+        Self {
+            temp: target.with_file_name(name),
+            target: target.to_owned(),
+            committed: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.temp
+    }
+
+    pub fn commit(mut self) -> Result<()> {
+        replace(&self.temp, &self.target)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempOutput {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn replace(from: &Path, to: &Path) -> io::Result<()> {
+    fs::rename(from, to)
+}
+
+// Renaming onto an existing file isn't guaranteed to succeed on Windows (e.g. when the
+// target is read-only or was opened without FILE_SHARE_DELETE), so fall back to removing it.
+#[cfg(windows)]
+fn replace(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(_) if to.exists() => {
+            fs::remove_file(to)?;
+            fs::rename(from, to)
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn claims_paths_that_must_not_be_clobbered() {
+        let dir = TempDir::new();
+        let path = dir.join("out.mp4");
+        let claimed = resolve_output_path(&path, WriteMode::FailIfExists).unwrap();
+        assert!(path.exists());
+        assert!(resolve_output_path(&path, WriteMode::FailIfExists).is_err());
+        drop(claimed);
+        assert!(!path.exists());
+        let mut kept = resolve_output_path(&path, WriteMode::FailIfExists).unwrap();
+        kept.keep();
+        drop(kept);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn appended_timestamps_never_collide() {
+        let dir = TempDir::new();
+        let path = dir.join("out.mp4");
+        let first = resolve_output_path(&path, WriteMode::AppendTimestamp).unwrap();
+        let second = resolve_output_path(&path, WriteMode::AppendTimestamp).unwrap();
+        assert_ne!(first.path(), second.path());
+        assert_eq!(first.path().extension(), Some("mp4".as_ref()));
+        assert!(first.path().exists() && second.path().exists());
+    }
+}
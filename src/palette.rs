@@ -0,0 +1,13 @@
+use crate::transform::Graph;
+use anyhow::Result;
+use ffmpeg::frame;
+
+// Each slide gets a palette of its own, since one for the whole deck would only be known once
+// every slide had been seen.
+const QUANTIZE: &str = "split[frame][colors];[colors]palettegen=stats_mode=single[palette];\
+                        [frame][palette]paletteuse=new=1";
+
+/// `frame` quantized to its own 256-color palette, the only kind of frame the GIF encoder takes.
+pub(crate) fn quantize(frame: &frame::Video) -> Result<frame::Video> {
+    Graph::new(QUANTIZE, (frame.format(), frame.width(), frame.height()))?.run_once(frame)
+}
@@ -0,0 +1,117 @@
+use crate::Profile;
+use anyhow::{anyhow, Result};
+use ffmpeg::{format::Pixel, Codec};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PixelFormat {
+    #[default]
+    Yuv420p,
+    Yuv420p10le,
+    Yuv420p12le,
+    Yuv422p,
+    Yuv422p10le,
+    Yuv422p12le,
+    Yuv444p,
+    Yuv444p10le,
+    Yuv444p12le,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitDepth {
+    #[default]
+    Eight,
+    Ten,
+    Twelve,
+}
+
+impl PixelFormat {
+    pub fn pixel(self) -> Pixel {
+        match self {
+            PixelFormat::Yuv420p => Pixel::YUV420P,
+            PixelFormat::Yuv420p10le => Pixel::YUV420P10LE,
+            PixelFormat::Yuv420p12le => Pixel::YUV420P12LE,
+            PixelFormat::Yuv422p => Pixel::YUV422P,
+            PixelFormat::Yuv422p10le => Pixel::YUV422P10LE,
+            PixelFormat::Yuv422p12le => Pixel::YUV422P12LE,
+            PixelFormat::Yuv444p => Pixel::YUV444P,
+            PixelFormat::Yuv444p10le => Pixel::YUV444P10LE,
+            PixelFormat::Yuv444p12le => Pixel::YUV444P12LE,
+        }
+    }
+
+    /// The format with the same chroma subsampling at `depth` bits per sample.
+    pub fn with_bit_depth(self, depth: BitDepth) -> Self {
+        use PixelFormat::*;
+        match (self.chroma_shift(), depth) {
+            ((1, 1), BitDepth::Eight) => Yuv420p,
+            ((1, 1), BitDepth::Ten) => Yuv420p10le,
+            ((1, 1), BitDepth::Twelve) => Yuv420p12le,
+            ((1, 0), BitDepth::Eight) => Yuv422p,
+            ((1, 0), BitDepth::Ten) => Yuv422p10le,
+            ((1, 0), BitDepth::Twelve) => Yuv422p12le,
+            (_, BitDepth::Eight) => Yuv444p,
+            (_, BitDepth::Ten) => Yuv444p10le,
+            (_, BitDepth::Twelve) => Yuv444p12le,
+        }
+    }
+
+    pub fn bit_depth(self) -> BitDepth {
+        use PixelFormat::*;
+        match self {
+            Yuv420p | Yuv422p | Yuv444p => BitDepth::Eight,
+            Yuv420p10le | Yuv422p10le | Yuv444p10le => BitDepth::Ten,
+            Yuv420p12le | Yuv422p12le | Yuv444p12le => BitDepth::Twelve,
+        }
+    }
+
+    /// Log2 of the horizontal and vertical chroma subsampling.
+    pub(crate) fn chroma_shift(self) -> (u32, u32) {
+        use PixelFormat::*;
+        match self {
+            Yuv420p | Yuv420p10le | Yuv420p12le => (1, 1),
+            Yuv422p | Yuv422p10le | Yuv422p12le => (1, 0),
+            Yuv444p | Yuv444p10le | Yuv444p12le => (0, 0),
+        }
+    }
+
+    /// The lowest H.264 profile able to carry this format, if above the encoder's default.
+    pub(crate) fn h264_profile(self) -> Option<Profile> {
+        use PixelFormat::*;
+        match self {
+            Yuv420p => None,
+            Yuv420p10le => Some(Profile::High10),
+            Yuv422p | Yuv422p10le => Some(Profile::High422),
+            Yuv420p12le | Yuv422p12le | Yuv444p | Yuv444p10le | Yuv444p12le => {
+                Some(Profile::High444)
+            }
+        }
+    }
+}
+
+impl BitDepth {
+    pub fn bits(self) -> u32 {
+        match self {
+            BitDepth::Eight => 8,
+            BitDepth::Ten => 10,
+            BitDepth::Twelve => 12,
+        }
+    }
+}
+
+pub(crate) fn ensure_supported(codec: Codec, format: Pixel) -> Result<()> {
+    let mut formats = match codec.video()?.formats() {
+        Some(formats) => formats,
+        None => return Ok(()),
+    };
+    if formats.any(|f| f == format) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "The {} encoder doesn't support the {:?} pixel format",
+            codec.name(),
+            format
+        ))
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::{anyhow, Result};
+use ffmpeg::{format::Pixel, Dictionary, Rational};
+
+// (level, max macroblocks per second, max frame size in macroblocks), from H.264 Table A-1.
+const LEVELS: [(Level, u64, u64); 19] = [
+    (Level::L10, 1_485, 99),
+    (Level::L11, 3_000, 396),
+    (Level::L12, 6_000, 396),
+    (Level::L13, 11_880, 396),
+    (Level::L20, 11_880, 396),
+    (Level::L21, 19_800, 792),
+    (Level::L22, 20_250, 1_620),
+    (Level::L30, 40_500, 1_620),
+    (Level::L31, 108_000, 3_600),
+    (Level::L32, 216_000, 5_120),
+    (Level::L40, 245_760, 8_192),
+    (Level::L41, 245_760, 8_192),
+    (Level::L42, 522_240, 8_704),
+    (Level::L50, 589_824, 22_080),
+    (Level::L51, 983_040, 36_864),
+    (Level::L52, 2_073_600, 36_864),
+    (Level::L60, 4_177_920, 139_264),
+    (Level::L61, 8_355_840, 139_264),
+    (Level::L62, 16_711_680, 139_264),
+];
+
+/// H.264 level, from Table A-1 of the spec.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Level {
+    L10,
+    L11,
+    L12,
+    L13,
+    L20,
+    L21,
+    L22,
+    L30,
+    L31,
+    L32,
+    L40,
+    L41,
+    L42,
+    L50,
+    L51,
+    L52,
+    L60,
+    L61,
+    L62,
+}
+
+impl Level {
+    /// The level as x264 and the spec write it, such as `"3.1"`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::L10 => "1.0",
+            Level::L11 => "1.1",
+            Level::L12 => "1.2",
+            Level::L13 => "1.3",
+            Level::L20 => "2.0",
+            Level::L21 => "2.1",
+            Level::L22 => "2.2",
+            Level::L30 => "3.0",
+            Level::L31 => "3.1",
+            Level::L32 => "3.2",
+            Level::L40 => "4.0",
+            Level::L41 => "4.1",
+            Level::L42 => "4.2",
+            Level::L50 => "5.0",
+            Level::L51 => "5.1",
+            Level::L52 => "5.2",
+            Level::L60 => "6.0",
+            Level::L61 => "6.1",
+            Level::L62 => "6.2",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Profile {
+    Baseline,
+    Main,
+    High,
+    High10,
+    High422,
+    High444,
+}
+
+impl Profile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Baseline => "baseline",
+            Profile::Main => "main",
+            Profile::High => "high",
+            Profile::High10 => "high10",
+            Profile::High422 => "high422",
+            Profile::High444 => "high444",
+        }
+    }
+
+    pub fn supports(self, format: Pixel) -> bool {
+        use Pixel::*;
+        match self {
+            Profile::Baseline | Profile::Main | Profile::High => format == YUV420P,
+            Profile::High10 => matches!(format, YUV420P | YUV420P10LE),
+            Profile::High422 => matches!(format, YUV420P | YUV420P10LE | YUV422P | YUV422P10LE),
+            Profile::High444 => true,
+        }
+    }
+}
+
+/// HEVC profile, for outputs encoded with [`ConvertOptions::codec`] set to HEVC.
+///
+/// [`ConvertOptions::codec`]: crate::ConvertOptions::codec
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum H265Profile {
+    Main,
+    Main10,
+    Main444,
+}
+
+impl H265Profile {
+    pub fn name(self) -> &'static str {
+        match self {
+            H265Profile::Main => "main",
+            H265Profile::Main10 => "main10",
+            H265Profile::Main444 => "main444-8",
+        }
+    }
+
+    pub fn supports(self, format: Pixel) -> bool {
+        use Pixel::*;
+        match self {
+            H265Profile::Main => format == YUV420P,
+            H265Profile::Main10 => matches!(format, YUV420P | YUV420P10LE),
+            H265Profile::Main444 => matches!(format, YUV420P | YUV422P | YUV444P),
+        }
+    }
+}
+
+fn auto_level(width: u32, height: u32, frame_rate: Rational) -> Result<Level> {
+    let (mbs_w, mbs_h) = ((width as u64).div_ceil(16), (height as u64).div_ceil(16));
+    let frame_size = mbs_w * mbs_h;
+    let fps = (frame_rate.numerator() as u64).div_ceil(frame_rate.denominator() as u64);
+    LEVELS
+        .iter()
+        .find(|&&(_, max_mbps, max_fs)| {
+            frame_size <= max_fs
+                && mbs_w * mbs_w <= 8 * max_fs
+                && mbs_h * mbs_h <= 8 * max_fs
+                && frame_size * fps <= max_mbps
+        })
+        .map(|&(level, _, _)| level)
+        .ok_or_else(|| {
+            anyhow!(
+                "{}x{} at {} fps exceeds the highest H.264 level",
+                width,
+                height,
+                fps
+            )
+        })
+}
+
+pub(crate) fn apply_h265(profile: H265Profile, format: Pixel, dict: &mut Dictionary) -> Result<()> {
+    if !profile.supports(format) {
+        return Err(anyhow!(
+            "HEVC profile {} doesn't support the {:?} pixel format",
+            profile.name(),
+            format
+        ));
+    }
+    dict.set("profile", profile.name());
+    Ok(())
+}
+
+pub(crate) fn apply(
+    profile: Option<Profile>,
+    level: Option<Level>,
+    format: Pixel,
+    (width, height): (u32, u32),
+    frame_rate: Rational,
+    dict: &mut Dictionary,
+) -> Result<()> {
+    if let Some(profile) = profile {
+        if !profile.supports(format) {
+            return Err(anyhow!(
+                "H.264 profile {} doesn't support the {:?} pixel format",
+                profile.name(),
+                format
+            ));
+        }
+        dict.set("profile", profile.name());
+    }
+    let level = match level {
+        Some(level) => level,
+        None => auto_level(width, height, frame_rate)?,
+    };
+    dict.set("level", level.name());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets_the_profile_and_level() {
+        let mut dict = Dictionary::new();
+        let size = (1280, 720);
+        apply(
+            Some(Profile::Baseline),
+            Some(Level::L31),
+            Pixel::YUV420P,
+            size,
+            Rational(30, 1),
+            &mut dict,
+        )
+        .unwrap();
+        assert_eq!(dict.get("profile"), Some("baseline"));
+        assert_eq!(dict.get("level"), Some("3.1"));
+        let mut dict = Dictionary::new();
+        let profile = Some(Profile::Baseline);
+        assert!(apply(
+            profile,
+            None,
+            Pixel::YUV444P,
+            size,
+            Rational(30, 1),
+            &mut dict
+        )
+        .is_err());
+        assert!(apply(
+            Some(Profile::High444),
+            None,
+            Pixel::YUV444P,
+            size,
+            Rational(30, 1),
+            &mut dict
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn picks_the_lowest_level_that_fits() {
+        assert_eq!(auto_level(640, 480, Rational(10, 1)).unwrap(), Level::L22);
+        assert_eq!(auto_level(1920, 1080, Rational(30, 1)).unwrap(), Level::L40);
+        assert_eq!(auto_level(1280, 720, Rational(100, 1)).unwrap(), Level::L42);
+        assert_eq!(auto_level(3840, 2160, Rational(30, 1)).unwrap(), Level::L51);
+        assert_eq!(auto_level(7680, 4320, Rational(1, 1)).unwrap(), Level::L60);
+        // A single row of macroblocks is tiny, but too wide for anything below level 5.1.
+        assert_eq!(auto_level(8192, 16, Rational(1, 1)).unwrap(), Level::L51);
+        assert!(auto_level(16384, 16384, Rational(1, 1)).is_err());
+    }
+}
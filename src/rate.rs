@@ -0,0 +1,166 @@
+use crate::DEFAULT_CRF;
+use anyhow::{anyhow, Result};
+use ffmpeg::{codec, encoder, Dictionary};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RateControl {
+    Crf(u8),
+    /// Constant quantizer, the same quality setting for every frame regardless of content.
+    Cqp(u8),
+    Vbr {
+        bitrate: usize,
+        maxrate: usize,
+        bufsize: usize,
+        two_pass: bool,
+    },
+    Cbr(usize),
+    /// Average bitrate, always encoded in two passes for the best quality at that size.
+    Abr(usize),
+}
+
+impl Default for RateControl {
+    fn default() -> Self {
+        RateControl::Crf(DEFAULT_CRF)
+    }
+}
+
+impl RateControl {
+    pub(crate) fn is_two_pass(self) -> bool {
+        matches!(
+            self,
+            RateControl::Vbr { two_pass: true, .. } | RateControl::Abr(_)
+        )
+    }
+
+    /// Fails if a quantizer is out of `codec`'s range.
+    pub(crate) fn validate(self, codec: codec::Id) -> Result<()> {
+        let max = match codec {
+            codec::Id::VP9 | codec::Id::AV1 => 63,
+            _ => 51,
+        };
+        match self {
+            RateControl::Cqp(qp) if qp > max => Err(anyhow!(
+                "Quantizer {} is out of range for {:?}, expected 0 to {}",
+                qp,
+                codec,
+                max
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn apply(self, encoder: &mut encoder::video::Video, dict: &mut Dictionary) {
+        match self {
+            RateControl::Crf(crf) => dict.set("crf", &crf.to_string()),
+            RateControl::Cqp(qp) => {
+                // libvpx has no qp option, but pinning its quantizer range does the same.
+                let qp = qp.to_string();
+                dict.set("qp", &qp);
+                dict.set("qmin", &qp);
+                dict.set("qmax", &qp);
+            }
+            RateControl::Vbr {
+                bitrate,
+                maxrate,
+                bufsize,
+                ..
+            } => {
+                encoder.set_bit_rate(bitrate);
+                encoder.set_max_bit_rate(maxrate);
+                dict.set("bufsize", &bufsize.to_string());
+            }
+            RateControl::Cbr(bitrate) => {
+                encoder.set_bit_rate(bitrate);
+                encoder.set_max_bit_rate(bitrate);
+                dict.set("minrate", &bitrate.to_string());
+                dict.set("bufsize", &bitrate.to_string());
+                dict.set("nal-hrd", "cbr");
+            }
+            RateControl::Abr(bitrate) => encoder.set_bit_rate(bitrate),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Pass<'a> {
+    Single,
+    First(&'a Path),
+    Second(&'a Path),
+}
+
+impl Pass<'_> {
+    pub fn flags(self) -> codec::Flags {
+        match self {
+            Pass::Single => codec::Flags::empty(),
+            Pass::First(_) => codec::Flags::PASS1,
+            Pass::Second(_) => codec::Flags::PASS2,
+        }
+    }
+
+    pub fn apply(self, dict: &mut Dictionary) {
+        if let Pass::First(stats) | Pass::Second(stats) = self {
+            dict.set("stats", &stats.to_string_lossy());
+        }
+    }
+}
+
+pub(crate) struct StatsFile(PathBuf);
+
+impl StatsFile {
+    pub fn new(output: &Path) -> Self {
+        let name = output.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        StatsFile(output.with_file_name(format!(".{}.slidevid_2pass.log", name)))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+// x264 also writes a macroblock tree file next to the stats file, and writes both to `.temp`
+// files first, which an interrupted first pass leaves behind.
+impl Drop for StatsFile {
+    fn drop(&mut self) {
+        for suffix in ["", ".temp", ".mbtree", ".mbtree.temp"].iter() {
+            let mut path = self.0.clone().into_os_string();
+            path.push(suffix);
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn checks_quantizers_against_the_codec() {
+        assert!(RateControl::Cqp(51).validate(codec::Id::H264).is_ok());
+        assert!(RateControl::Cqp(52).validate(codec::Id::H264).is_err());
+        assert!(RateControl::Cqp(52).validate(codec::Id::HEVC).is_err());
+        assert!(RateControl::Cqp(63).validate(codec::Id::VP9).is_ok());
+        assert!(RateControl::Cqp(64).validate(codec::Id::AV1).is_err());
+        assert!(RateControl::Crf(60).validate(codec::Id::H264).is_ok());
+    }
+
+    #[test]
+    fn stats_files_are_removed_on_drop() {
+        let dir = TempDir::new();
+        let stats = StatsFile::new(&dir.join("out.mp4"));
+        let mut paths = Vec::new();
+        for suffix in ["", ".temp", ".mbtree", ".mbtree.temp"].iter() {
+            let mut path = stats.path().to_owned().into_os_string();
+            path.push(suffix);
+            fs::write(&path, b"x264 stats").unwrap();
+            paths.push(PathBuf::from(path));
+        }
+        drop(stats);
+        assert!(paths.iter().all(|path| !path.exists()));
+    }
+}
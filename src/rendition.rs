@@ -0,0 +1,91 @@
+use crate::{
+    encode_renditions, error, output, validate_output_format, ConvertOptions, EncodeStats, Frame,
+    Pass, ZipSource,
+};
+use anyhow::{anyhow, Result};
+use ffmpeg::format;
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+/// One output of [`convert_multi_rendition`], scaled to `height` with the slides' aspect ratio.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rendition {
+    pub output_path: PathBuf,
+    pub height: u32,
+}
+
+impl Rendition {
+    pub fn new(output_path: impl Into<PathBuf>, height: u32) -> Self {
+        Self {
+            output_path: output_path.into(),
+            height,
+        }
+    }
+}
+
+/// Encodes the deck once per rendition, decoding each slide only once for all of them.
+pub fn convert_multi_rendition<Z: AsRef<[u8]>, S: AsRef<str>>(
+    zip: Z,
+    frames: &[Frame<S>],
+    renditions: &[Rendition],
+    options: &ConvertOptions,
+) -> Result<Vec<EncodeStats>> {
+    if renditions.is_empty() {
+        return Err(anyhow!("No renditions to encode"));
+    }
+    if options.rate_control.is_two_pass() {
+        return Err(anyhow!("Two-pass encoding isn't supported for renditions"));
+    }
+    if options.dry_run {
+        return Err(anyhow!("Dry runs aren't supported for renditions"));
+    }
+    let mut paths = renditions
+        .iter()
+        .map(|rendition| {
+            validate_output_format(&rendition.output_path, options.container)?;
+            output::resolve_output_path(&rendition.output_path, options.write_mode)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let source = &mut ZipSource::with_options(Cursor::new(zip.as_ref()), options)?;
+    let temps: Option<Vec<_>> = options.atomic.then(|| {
+        paths
+            .iter()
+            .map(|path| output::TempOutput::new(path.path()))
+            .collect()
+    });
+    let write_paths: Vec<&Path> = match &temps {
+        Some(temps) => temps.iter().map(output::TempOutput::path).collect(),
+        None => paths.iter().map(output::OutputPath::path).collect(),
+    };
+    let mut outputs = write_paths
+        .iter()
+        .map(|path| format::output_as(path, options.container.format_name()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let result = encode_renditions(
+        source,
+        frames,
+        outputs
+            .iter_mut()
+            .zip(renditions)
+            .map(|(output, rendition)| (output, Some(rendition.height)))
+            .collect(),
+        options,
+        Pass::Single,
+    );
+    // The outputs have to be closed before they can be moved into place.
+    drop(outputs);
+    if !options.atomic || error::wrote_output(&result) {
+        for temp in temps.into_iter().flatten() {
+            temp.commit()?;
+        }
+        paths.iter_mut().for_each(output::OutputPath::keep);
+    }
+    let mut stats = result?;
+    for (stats, path) in stats.iter_mut().zip(&paths) {
+        stats.output_path = path.path().to_owned();
+    }
+    Ok(stats)
+}
@@ -0,0 +1,118 @@
+use crate::{output::TempOutput, timing, ConvertOptions, EncodeStats, Frame};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+/// Summary of a conversion written to [`ConvertOptions::report_path`].
+///
+/// [`ConvertOptions::report_path`]: crate::ConvertOptions::report_path
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EncodeReport {
+    /// Whether the conversion succeeded; only partial reports can have this unset.
+    pub complete: bool,
+    pub error: Option<String>,
+    pub output_path: Option<PathBuf>,
+    pub frame_count: usize,
+    /// Width and height of each slide as decoded, for the slides that were encoded.
+    pub slide_sizes: Vec<(u32, u32)>,
+    pub codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub duration_ms: u64,
+    pub output_size: Option<u64>,
+    pub options: ConvertOptions,
+}
+
+impl EncodeReport {
+    fn new<S: AsRef<str>>(
+        result: &Result<EncodeStats>,
+        frames: &[Frame<S>],
+        options: &ConvertOptions,
+    ) -> Self {
+        let delays = timing::select_frames(frames, options)
+            .and_then(|frames| timing::resolve_delays(frames, options))
+            .unwrap_or_default();
+        let mut report = Self {
+            complete: result.is_ok(),
+            error: result.as_ref().err().map(|err| format!("{:#}", err)),
+            output_path: None,
+            frame_count: delays.len(),
+            slide_sizes: Vec::new(),
+            codec: None,
+            width: None,
+            height: None,
+            duration_ms: delays.iter().map(|&delay| delay as u64).sum(),
+            output_size: None,
+            options: options.clone(),
+        };
+        if let Ok(stats) = result {
+            // Slides skipped by `skip_bad_leading_slides` aren't in the video, nor in the sizes.
+            let skipped = delays.len().saturating_sub(stats.slide_sizes.len());
+            report.frame_count -= skipped;
+            report.duration_ms = delays[skipped..].iter().map(|&delay| delay as u64).sum();
+            let params = unsafe { &*stats.codec_params.as_ptr() };
+            report.output_path = Some(stats.output_path.clone());
+            report.slide_sizes = stats.slide_sizes.clone();
+            report.codec = Some(format!("{:?}", stats.codec_params.id()));
+            report.width = Some(params.width as u32);
+            report.height = Some(params.height as u32);
+            report.output_size = fs::metadata(&stats.output_path)
+                .ok()
+                .map(|metadata| metadata.len());
+        }
+        report
+    }
+}
+
+/// Writes the report for `result` if one was asked for, failed conversions only being reported
+/// with [`ConvertOptions::partial_report`]. A conversion error takes precedence over an error
+/// writing the report.
+pub(crate) fn write<S: AsRef<str>>(
+    result: Result<EncodeStats>,
+    frames: &[Frame<S>],
+    options: &ConvertOptions,
+) -> Result<EncodeStats> {
+    let path = match &options.report_path {
+        Some(path) if result.is_ok() || options.partial_report => path,
+        _ => return result,
+    };
+    let report = EncodeReport::new(&result, frames, options);
+    let temp = TempOutput::new(path);
+    let written = serde_json::to_vec_pretty(&report)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| Ok(fs::write(temp.path(), json)?))
+        .and_then(|()| temp.commit());
+    let stats = result?;
+    written?;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_round_trip_through_json() {
+        let report = EncodeReport {
+            complete: false,
+            error: Some("Encoding was cancelled".to_owned()),
+            output_path: Some(PathBuf::from("out.mp4")),
+            frame_count: 2,
+            slide_sizes: vec![(1280, 720), (1280, 720)],
+            codec: Some("H264".to_owned()),
+            width: Some(1280),
+            height: Some(720),
+            duration_ms: 4_500,
+            output_size: Some(123_456),
+            options: ConvertOptions::new().min_slide_ms(100).end_hold_ms(500),
+        };
+        let json = serde_json::to_string(&report).unwrap();
+        let parsed: EncodeReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.slide_sizes, report.slide_sizes);
+        assert_eq!(parsed.options.min_slide_ms, Some(100));
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&report).unwrap()
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use ffmpeg::software::scaling;
+
+/// How a slide is resampled to the output size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScaleAlgorithm {
+    #[default]
+    Lanczos,
+    Bicubic,
+    Bilinear,
+    Area,
+    /// Keeps hard pixel edges, for pixel art and screenshots scaled by whole multiples.
+    Nearest,
+}
+
+impl ScaleAlgorithm {
+    pub(crate) fn flags(self) -> scaling::Flags {
+        match self {
+            ScaleAlgorithm::Lanczos => scaling::Flags::LANCZOS,
+            ScaleAlgorithm::Bicubic => scaling::Flags::BICUBIC,
+            ScaleAlgorithm::Bilinear => scaling::Flags::BILINEAR,
+            ScaleAlgorithm::Area => scaling::Flags::AREA,
+            ScaleAlgorithm::Nearest => scaling::Flags::POINT,
+        }
+    }
+
+    /// The algorithm's name for the `flags` option of the `scale` filter.
+    pub(crate) fn filter_flags(self) -> &'static str {
+        match self {
+            ScaleAlgorithm::Lanczos => "lanczos",
+            ScaleAlgorithm::Bicubic => "bicubic",
+            ScaleAlgorithm::Bilinear => "bilinear",
+            ScaleAlgorithm::Area => "area",
+            ScaleAlgorithm::Nearest => "neighbor",
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use anyhow::{anyhow, Result};
+use ffmpeg::frame::{self, side_data::Type::SEI_UNREGISTERED};
+use std::slice;
+
+pub const SEI_UUID: [u8; 16] = *b"slidevid-meta-v1";
+
+pub(crate) fn attach(frame: &mut frame::Video, metadata: Option<&[u8]>) -> Result<()> {
+    frame.remove_side_data(SEI_UNREGISTERED);
+    let metadata = match metadata {
+        Some(metadata) => metadata,
+        None => return Ok(()),
+    };
+    let mut side_data = frame
+        .new_side_data(SEI_UNREGISTERED, SEI_UUID.len() + metadata.len())
+        .ok_or_else(|| anyhow!("Couldn't allocate SEI side data"))?;
+    let data = unsafe {
+        let ptr = side_data.as_mut_ptr();
+        slice::from_raw_parts_mut((*ptr).data, (*ptr).size as usize)
+    };
+    let (uuid, payload) = data.split_at_mut(SEI_UUID.len());
+    uuid.copy_from_slice(&SEI_UUID);
+    payload.copy_from_slice(metadata);
+    Ok(())
+}
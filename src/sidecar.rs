@@ -0,0 +1,151 @@
+use crate::{timing, ConvertOptions, Frame};
+use anyhow::Result;
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const ASS_HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 384
+PlayResY: 288
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, \
+Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, \
+Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Sans,16,&H00FFFFFF,&H00FFFFFF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,1,0,\
+2,10,10,10,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+/// Writes [`generate_srt`]'s file next to `output_path`, returning the path written to.
+pub fn write_srt<S: AsRef<str>, O: AsRef<Path>>(
+    output_path: O,
+    frames: &[Frame<S>],
+    options: &ConvertOptions,
+) -> Result<PathBuf> {
+    let srt = generate_srt(frames, options)?;
+    let path = output_path.as_ref().with_extension("srt");
+    fs::write(&path, srt)?;
+    Ok(path)
+}
+
+/// Formats the frames' subtitles as an SRT file, timed like an encode with `options`.
+pub fn generate_srt<S: AsRef<str>>(
+    frames: &[Frame<S>],
+    options: &ConvertOptions,
+) -> Result<String> {
+    let frames = timing::select_frames(frames, options)?;
+    let delays = timing::resolve_delays(frames, options)?;
+    Ok(srt(cues(frames, &delays)))
+}
+
+/// Formats the frames' subtitles as an ASS file, timed like an encode with `options`.
+pub fn generate_ass<S: AsRef<str>>(
+    frames: &[Frame<S>],
+    options: &ConvertOptions,
+) -> Result<String> {
+    let frames = timing::select_frames(frames, options)?;
+    let delays = timing::resolve_delays(frames, options)?;
+    let mut ass = ASS_HEADER.to_owned();
+    for (text, (start, end)) in cues(frames, &delays) {
+        writeln!(
+            ass,
+            "Dialogue: 0,{},{},Default,,0,0,0,,{}",
+            ass_timestamp(start),
+            ass_timestamp(end),
+            text.replace("\r\n", "\\N").replace('\n', "\\N")
+        )
+        .unwrap();
+    }
+    Ok(ass)
+}
+
+/// Each frame's subtitle with the span of its slide, in milliseconds.
+fn cues<'a, S: AsRef<str>>(
+    frames: &'a [Frame<S>],
+    delays: &'a [u32],
+) -> impl Iterator<Item = (&'a str, (i64, i64))> {
+    frames
+        .iter()
+        .zip(timing::slide_spans(delays))
+        .filter_map(|(frame, span)| Some((frame.subtitle.as_deref()?, span)))
+        .filter(|(text, _)| !text.is_empty())
+}
+
+fn srt<'a>(cues: impl Iterator<Item = (&'a str, (i64, i64))>) -> String {
+    let mut srt = String::new();
+    for (index, (text, (start, end))) in cues.enumerate() {
+        writeln!(
+            srt,
+            "{}\n{} --> {}\n{}\n",
+            index + 1,
+            srt_timestamp(start),
+            srt_timestamp(end),
+            text
+        )
+        .unwrap();
+    }
+    srt
+}
+
+fn srt_timestamp(ms: i64) -> String {
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1_000 % 60,
+        ms % 1_000
+    )
+}
+
+fn ass_timestamp(ms: i64) -> String {
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1_000 % 60,
+        ms % 1_000 / 10
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_defaulted_delays_like_the_encode() {
+        let frames = [
+            Frame::new("0.png", 1_500).with_subtitle("First"),
+            Frame::without_delay("1.png").with_subtitle("Second"),
+        ];
+        let options = ConvertOptions::new().default_delay_ms(2_000);
+        assert_eq!(
+            generate_srt(&frames, &options).unwrap(),
+            "1\n00:00:00,000 --> 00:00:01,500\nFirst\n\n\
+             2\n00:00:01,500 --> 00:00:03,500\nSecond\n\n"
+        );
+        assert!(generate_srt(&frames, &ConvertOptions::new()).is_err());
+    }
+
+    #[test]
+    fn writes_cues_at_the_slide_boundaries() {
+        let frames = [
+            Frame::new("0.png", 1_000).with_subtitle("Intro"),
+            Frame::new("1.png", 250),
+            Frame::new("2.png", 61_750).with_subtitle("Two\nlines"),
+        ];
+        let dir = crate::test_util::TempDir::new();
+        let path = write_srt(dir.join("deck.mp4"), &frames, &ConvertOptions::new()).unwrap();
+        assert_eq!(path, dir.join("deck.srt"));
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "1\n00:00:00,000 --> 00:00:01,000\nIntro\n\n\
+             2\n00:00:01,250 --> 00:01:03,000\nTwo\nlines\n\n"
+        );
+    }
+}
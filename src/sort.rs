@@ -0,0 +1,134 @@
+use std::{cmp::Ordering, iter::Peekable, str::CharIndices};
+
+/// Compares filenames the way people read them: runs of digits by their numeric value, so
+/// `img2.png` comes before `img10.png`, and letters regardless of case. Names that only differ
+/// in zero padding or case fall back to plain string order.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chunks, mut b_chunks) = (Chunks::new(a), Chunks::new(b));
+    loop {
+        let ordering = match (a_chunks.next(), b_chunks.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) if is_number(a) && is_number(b) => {
+                let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+                a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+            }
+            (Some(a), Some(b)) => a
+                .chars()
+                .flat_map(char::to_lowercase)
+                .cmp(b.chars().flat_map(char::to_lowercase)),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Sorts `names` in [`natural_cmp`] order.
+pub fn natural_sort(names: &mut [&str]) {
+    names.sort_by(|a, b| natural_cmp(a, b));
+}
+
+fn is_number(chunk: &str) -> bool {
+    chunk.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Splits a string into alternating runs of ASCII digits and everything else.
+struct Chunks<'a> {
+    s: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Chunks<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            s,
+            chars: s.char_indices().peekable(),
+        }
+    }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let (start, first) = self.chars.next()?;
+        let digits = first.is_ascii_digit();
+        let mut end = start + first.len_utf8();
+        while let Some(&(index, c)) = self.chars.peek() {
+            if c.is_ascii_digit() != digits {
+                break;
+            }
+            end = index + c.len_utf8();
+            self.chars.next();
+        }
+        Some(&self.s[start..end])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_numbers_by_value() {
+        let mut names = ["img100.png", "img10.png", "img2.png", "img1.png"];
+        natural_sort(&mut names);
+        assert_eq!(names, ["img1.png", "img2.png", "img10.png", "img100.png"]);
+    }
+
+    #[test]
+    fn ignores_case_and_zero_padding() {
+        let mut names = [
+            "Slide10.png",
+            "slide9.png",
+            "IMG3.png",
+            "img02.png",
+            "img2.png",
+        ];
+        natural_sort(&mut names);
+        assert_eq!(
+            names,
+            [
+                "img02.png",
+                "img2.png",
+                "IMG3.png",
+                "slide9.png",
+                "Slide10.png"
+            ]
+        );
+        assert_eq!(natural_cmp("a1b2", "A1B2"), "a1b2".cmp("A1B2"));
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+        assert_eq!(natural_cmp("page", "page1"), Ordering::Less);
+    }
+}
+
+#[cfg(all(test, feature = "test-proptest"))]
+mod proptests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Short names from a few letters, digits and a non-ASCII one, so that comparisons often get
+    // past the first chunk and into zero padding and case.
+    const NAME: &str = "[aAbB0-9._é]{0,8}";
+
+    proptest! {
+        #[test]
+        fn natural_cmp_is_antisymmetric(a in NAME, b in NAME) {
+            prop_assert_eq!(natural_cmp(&a, &b), natural_cmp(&b, &a).reverse());
+            prop_assert_eq!(natural_cmp(&a, &b) == Ordering::Equal, a == b);
+        }
+
+        #[test]
+        fn natural_cmp_is_transitive(names in prop::collection::vec(NAME, 0..20)) {
+            let mut names: Vec<_> = names.iter().map(String::as_str).collect();
+            natural_sort(&mut names);
+            for (i, a) in names.iter().enumerate() {
+                for b in &names[i..] {
+                    prop_assert!(natural_cmp(a, b) != Ordering::Greater, "{:?} > {:?}", a, b);
+                }
+            }
+        }
+    }
+}
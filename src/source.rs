@@ -0,0 +1,515 @@
+use crate::{ConvertOptions, Frame, SlidevidError};
+use anyhow::{anyhow, Result};
+#[cfg(feature = "image")]
+use ffmpeg::format::Pixel;
+use ffmpeg::frame;
+#[cfg(feature = "tar")]
+use std::io::{BufRead, BufReader};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    io::{self, Cursor, Read, Seek},
+    path::PathBuf,
+};
+use zip::{result::ZipError, ZipArchive};
+
+/// Most bytes preallocated for an entry, since archive headers can claim any size.
+const MAX_PREALLOCATION: u64 = 64 << 20;
+
+/// Where slide images are read from, by the filename given in each [`Frame`](crate::Frame).
+pub trait FrameSource {
+    fn read_frame(&mut self, name: &str) -> Result<Vec<u8>>;
+
+    /// The slide `name` already decoded, for sources that hold pixels rather than image files,
+    /// or `None` to have it read with [`read_frame`](FrameSource::read_frame) and decoded.
+    fn read_decoded(&mut self, _name: &str) -> Result<Option<frame::Video>> {
+        Ok(None)
+    }
+}
+
+pub struct ZipSource<R> {
+    archive: ZipArchive<R>,
+    max_frame_bytes: Option<u64>,
+    password: Option<Vec<u8>>,
+    loose_entry_names: bool,
+}
+
+impl<R: Read + Seek> ZipSource<R> {
+    pub fn new(reader: R) -> Result<Self> {
+        let archive = ZipArchive::new(reader)?;
+        if archive.is_empty() {
+            return Err(SlidevidError::EmptyArchive.into());
+        }
+        Ok(Self {
+            archive,
+            max_frame_bytes: None,
+            password: None,
+            loose_entry_names: false,
+        })
+    }
+
+    /// Rejects entries that decompress to more than `max_frame_bytes` bytes.
+    pub fn max_frame_bytes(mut self, max_frame_bytes: u64) -> Self {
+        self.max_frame_bytes = Some(max_frame_bytes);
+        self
+    }
+
+    /// Decrypts encrypted entries with `password`.
+    pub fn password(mut self, password: Vec<u8>) -> Self {
+        self.password = Some(password);
+        self
+    }
+
+    /// Finds frames missing from the archive under extra leading directories, or failing that
+    /// by their basename, as long as only one entry matches.
+    pub fn loose_entry_names(mut self, loose_entry_names: bool) -> Self {
+        self.loose_entry_names = loose_entry_names;
+        self
+    }
+
+    pub(crate) fn with_options(reader: R, options: &ConvertOptions) -> Result<Self> {
+        let mut source = Self::new(reader)?;
+        source.max_frame_bytes = options.max_frame_bytes;
+        source.password = options.password.clone();
+        source.loose_entry_names = options.loose_entry_names;
+        Ok(source)
+    }
+
+    fn entry_name<'n>(&self, name: &'n str) -> Result<Cow<'n, str>> {
+        if !self.loose_entry_names || self.archive.file_names().any(|entry| entry == name) {
+            return Ok(Cow::Borrowed(name));
+        }
+        let files = || {
+            self.archive
+                .file_names()
+                .filter(|entry| !entry.ends_with('/'))
+        };
+        let basename = name.rsplit('/').next();
+        let suffix = format!("/{}", name.trim_start_matches('/'));
+        let mut matches: Vec<_> = files().filter(|entry| entry.ends_with(&suffix)).collect();
+        if matches.is_empty() {
+            matches = files()
+                .filter(|entry| entry.rsplit('/').next() == basename)
+                .collect();
+        }
+        match matches.as_slice() {
+            [entry] => Ok(Cow::Owned((*entry).to_owned())),
+            [] => {
+                let mut available: Vec<_> = files().map(str::to_owned).collect();
+                available.sort_unstable();
+                Err(SlidevidError::MissingFrame {
+                    name: name.to_owned(),
+                    available,
+                }
+                .into())
+            }
+            entries => Err(anyhow!(
+                "Frame {:?} matches several entries in the archive: {}",
+                name,
+                entries.join(", ")
+            )),
+        }
+    }
+}
+
+impl<R: Read + Seek> FrameSource for ZipSource<R> {
+    fn read_frame(&mut self, name: &str) -> Result<Vec<u8>> {
+        let entry = self.entry_name(name)?;
+        let name = entry.as_ref();
+        let file = match &self.password {
+            Some(password) => self
+                .archive
+                .by_name_decrypt(name, password)
+                .map_err(|err| zip_error(err, name))?
+                .map_err(|_| SlidevidError::WrongPassword(name.to_owned()))?,
+            None => self
+                .archive
+                .by_name(name)
+                .map_err(|err| zip_error(err, name))?,
+        };
+        let size = file.size();
+        if let Some(max) = self.max_frame_bytes.filter(|&max| size > max) {
+            return Err(SlidevidError::FrameTooLarge(name.to_owned(), size, max).into());
+        }
+        // The header's size can't be trusted, so reading one byte past it catches entries that
+        // decompress to more, without reading the whole stream.
+        let capacity = size.min(self.max_frame_bytes.unwrap_or(MAX_PREALLOCATION));
+        let mut data = Vec::with_capacity(capacity as usize);
+        file.take(size + 1).read_to_end(&mut data)?;
+        if data.len() as u64 != size {
+            return Err(anyhow!(
+                "{:?} decompressed to a different size than the {} bytes the archive reports",
+                name,
+                size
+            ));
+        }
+        Ok(data)
+    }
+}
+
+fn zip_error(err: ZipError, name: &str) -> anyhow::Error {
+    match err {
+        ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => {
+            SlidevidError::EncryptedArchive(name.to_owned()).into()
+        }
+        ZipError::FileNotFound => SlidevidError::missing_frame(name).into(),
+        err => err.into(),
+    }
+}
+
+pub struct DirSource(PathBuf);
+
+impl DirSource {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self(dir.into())
+    }
+}
+
+impl FrameSource for DirSource {
+    fn read_frame(&mut self, name: &str) -> Result<Vec<u8>> {
+        match fs::read(self.0.join(name)) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                Err(SlidevidError::missing_frame(name).into())
+            }
+            result => Ok(result?),
+        }
+    }
+}
+
+impl FrameSource for HashMap<String, Vec<u8>> {
+    fn read_frame(&mut self, name: &str) -> Result<Vec<u8>> {
+        self.get(name)
+            .cloned()
+            .ok_or_else(|| SlidevidError::missing_frame(name).into())
+    }
+}
+
+/// A tarball, optionally gzipped, unpacked into memory up front since it can't be read out of
+/// order. Every file in it is held in memory until the conversion is done, so slides unused by
+/// the frames still cost their size.
+#[cfg(feature = "tar")]
+pub struct TarSource(HashMap<String, Vec<u8>>);
+
+#[cfg(feature = "tar")]
+impl TarSource {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let files = if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+            unpack_tar(tar::Archive::new(flate2::read::GzDecoder::new(reader)))?
+        } else {
+            unpack_tar(tar::Archive::new(reader))?
+        };
+        if files.is_empty() {
+            return Err(SlidevidError::EmptyArchive.into());
+        }
+        Ok(Self(files))
+    }
+}
+
+#[cfg(feature = "tar")]
+fn unpack_tar<R: Read>(mut archive: tar::Archive<R>) -> Result<HashMap<String, Vec<u8>>> {
+    let mut files = HashMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::with_capacity(entry.size().min(MAX_PREALLOCATION) as usize);
+        entry.read_to_end(&mut data)?;
+        files.insert(name.trim_start_matches("./").to_owned(), data);
+    }
+    Ok(files)
+}
+
+#[cfg(feature = "tar")]
+impl FrameSource for TarSource {
+    fn read_frame(&mut self, name: &str) -> Result<Vec<u8>> {
+        self.0.read_frame(name)
+    }
+}
+
+/// A 7z archive, unpacked into memory up front since solid archives can't be read out of order.
+#[cfg(feature = "sevenz")]
+pub struct SevenZipSource(HashMap<String, Vec<u8>>);
+
+#[cfg(feature = "sevenz")]
+impl SevenZipSource {
+    pub fn new<R: Read + Seek>(mut reader: R) -> Result<Self> {
+        let len = reader.seek(std::io::SeekFrom::End(0))?;
+        reader.rewind()?;
+        let mut archive =
+            sevenz_rust::SevenZReader::new(reader, len, sevenz_rust::Password::empty())?;
+        let mut files = HashMap::new();
+        archive.for_each_entries(|entry, file| {
+            if !entry.is_directory() {
+                let mut data = Vec::with_capacity(entry.size().min(MAX_PREALLOCATION) as usize);
+                file.read_to_end(&mut data)?;
+                files.insert(entry.name().to_owned(), data);
+            }
+            Ok(true)
+        })?;
+        if files.is_empty() {
+            return Err(SlidevidError::EmptyArchive.into());
+        }
+        Ok(Self(files))
+    }
+}
+
+#[cfg(feature = "sevenz")]
+impl FrameSource for SevenZipSource {
+    fn read_frame(&mut self, name: &str) -> Result<Vec<u8>> {
+        self.0.read_frame(name)
+    }
+}
+
+/// Already decoded images, handed to the encoder as they are in the FFmpeg pixel format laid
+/// out like them. 16-bit images keep their depth, and floating point ones, which no packed
+/// FFmpeg format matches, are taken to 16 bits.
+#[cfg(feature = "image")]
+pub struct ImageSource(Vec<image::DynamicImage>);
+
+#[cfg(feature = "image")]
+impl ImageSource {
+    /// Takes each image with its delay in milliseconds, returning the frames to encode them with,
+    /// which are named by index.
+    pub fn new(images: Vec<(image::DynamicImage, u32)>) -> (Self, Vec<Frame<String>>) {
+        let frames = (0..images.len())
+            .zip(images.iter())
+            .map(|(index, (_, delay))| Frame::new(index.to_string(), *delay))
+            .collect();
+        let images = images.into_iter().map(|(image, _)| image).collect();
+        (Self(images), frames)
+    }
+
+    fn image(&self, name: &str) -> Result<&image::DynamicImage> {
+        name.parse::<usize>()
+            .ok()
+            .and_then(|index| self.0.get(index))
+            .ok_or_else(|| SlidevidError::missing_frame(name).into())
+    }
+}
+
+#[cfg(feature = "image")]
+impl FrameSource for ImageSource {
+    /// The image's samples as stored, which no decoder reads; conversions take the frame from
+    /// [`read_decoded`](FrameSource::read_decoded) instead.
+    fn read_frame(&mut self, name: &str) -> Result<Vec<u8>> {
+        Ok(self.image(name)?.as_bytes().to_vec())
+    }
+
+    fn read_decoded(&mut self, name: &str) -> Result<Option<frame::Video>> {
+        use image::DynamicImage::*;
+        let image = self.image(name)?;
+        let native = |little, big| {
+            if cfg!(target_endian = "little") {
+                little
+            } else {
+                big
+            }
+        };
+        let converted;
+        let (format, image) = match image {
+            ImageLuma8(_) => (Pixel::GRAY8, image),
+            ImageLumaA8(_) => (Pixel::YA8, image),
+            ImageRgb8(_) => (Pixel::RGB24, image),
+            ImageRgba8(_) => (Pixel::RGBA, image),
+            ImageLuma16(_) => (native(Pixel::GRAY16LE, Pixel::GRAY16BE), image),
+            ImageLumaA16(_) => (native(Pixel::YA16LE, Pixel::YA16BE), image),
+            ImageRgb16(_) => (native(Pixel::RGB48LE, Pixel::RGB48BE), image),
+            ImageRgba16(_) => (native(Pixel::RGBA64LE, Pixel::RGBA64BE), image),
+            _ if image.color().has_alpha() => {
+                converted = ImageRgba16(image.to_rgba16());
+                (native(Pixel::RGBA64LE, Pixel::RGBA64BE), &converted)
+            }
+            _ => {
+                converted = ImageRgb16(image.to_rgb16());
+                (native(Pixel::RGB48LE, Pixel::RGB48BE), &converted)
+            }
+        };
+        let mut video = frame::Video::new(format, image.width(), image.height());
+        let row = image.width() as usize * image.color().bytes_per_pixel() as usize;
+        let stride = video.stride(0);
+        let rows = video.data_mut(0).chunks_mut(stride);
+        for (dst, src) in rows.zip(image.as_bytes().chunks_exact(row)) {
+            dst[..row].copy_from_slice(src);
+        }
+        Ok(Some(video))
+    }
+}
+
+/// Several zip archives read as one, with each frame name prefixed by its archive's index.
+pub(crate) struct ConcatSource<'a>(Vec<ZipSource<Cursor<&'a [u8]>>>);
+
+impl<'a> ConcatSource<'a> {
+    pub(crate) fn new<Z: AsRef<[u8]>, S: AsRef<str>>(
+        sources: &'a [(Z, &[Frame<S>])],
+        options: &ConvertOptions,
+    ) -> Result<(Self, Vec<Frame<String>>)> {
+        let mut archives = Vec::with_capacity(sources.len());
+        let mut frames = Vec::new();
+        for (index, (zip, source_frames)) in sources.iter().enumerate() {
+            archives.push(ZipSource::with_options(Cursor::new(zip.as_ref()), options)?);
+            frames.extend(source_frames.iter().map(|frame| Frame {
+                filename: format!("{}/{}", index, frame.filename.as_ref()),
+                delay: frame.delay,
+                metadata: frame.metadata.clone(),
+                chapter_title: frame.chapter_title.clone(),
+                subtitle: frame.subtitle.clone(),
+                scaling: frame.scaling,
+                size: frame.size,
+                transform: frame.transform,
+            }));
+        }
+        Ok((Self(archives), frames))
+    }
+}
+
+impl FrameSource for ConcatSource<'_> {
+    fn read_frame(&mut self, name: &str) -> Result<Vec<u8>> {
+        let (index, name) = name
+            .split_once('/')
+            .and_then(|(index, name)| Some((index.parse::<usize>().ok()?, name)))
+            .ok_or_else(|| SlidevidError::missing_frame(name))?;
+        match self.0.get_mut(index) {
+            Some(archive) => archive.read_frame(name),
+            None => Err(SlidevidError::missing_frame(format!("{}/{}", index, name)).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{make_encrypted_zip, make_zip, TempDir};
+
+    #[test]
+    fn reads_slides_from_a_directory() {
+        let dir = TempDir::new();
+        fs::write(dir.join("0.png"), b"first").unwrap();
+        let mut source = DirSource::new(dir.join(""));
+        assert_eq!(source.read_frame("0.png").unwrap(), b"first");
+        let err = source.read_frame("1.png").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(SlidevidError::MissingFrame { name, .. }) if name == "1.png"
+        ));
+    }
+
+    #[test]
+    fn rejects_oversized_entries() {
+        let zip = make_zip(&[("small.png", vec![0; 100]), ("large.png", vec![0; 1_000])]);
+        let mut source = ZipSource::new(Cursor::new(zip))
+            .unwrap()
+            .max_frame_bytes(500);
+        assert_eq!(source.read_frame("small.png").unwrap().len(), 100);
+        let err = source.read_frame("large.png").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(SlidevidError::FrameTooLarge(name, 1_000, 500)) if name == "large.png"
+        ));
+    }
+
+    #[test]
+    fn decrypts_entries_with_the_password() {
+        let zip = make_encrypted_zip(
+            &[("0.png", &b"first slide"[..]), ("1.png", b"second")],
+            b"secret",
+        );
+        let source = |password: Option<&[u8]>| {
+            let source = ZipSource::new(Cursor::new(zip.clone())).unwrap();
+            match password {
+                Some(password) => source.password(password.to_vec()),
+                None => source,
+            }
+        };
+        let err = source(None).read_frame("0.png").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(SlidevidError::EncryptedArchive(name)) if name == "0.png"
+        ));
+        let err = source(Some(b"wrong")).read_frame("1.png").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(SlidevidError::WrongPassword(name)) if name == "1.png"
+        ));
+        let mut decrypted = source(Some(b"secret"));
+        assert_eq!(decrypted.read_frame("0.png").unwrap(), b"first slide");
+        assert_eq!(decrypted.read_frame("1.png").unwrap(), b"second");
+        let err = decrypted.read_frame("2.png").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(SlidevidError::MissingFrame { name, .. }) if name == "2.png"
+        ));
+    }
+
+    #[test]
+    fn finds_entries_under_extra_directories() {
+        let zip = make_zip(&[
+            ("export/slides/img1.png", &b"one"[..]),
+            ("export/slides/img2.png", b"two"),
+            ("export/notes/img2.png", b"notes"),
+            ("export/title.png", b"title"),
+        ]);
+        let mut strict = ZipSource::new(Cursor::new(zip.clone())).unwrap();
+        assert!(strict.read_frame("slides/img1.png").is_err());
+        let mut source = ZipSource::new(Cursor::new(zip))
+            .unwrap()
+            .loose_entry_names(true);
+        assert_eq!(source.read_frame("slides/img1.png").unwrap(), b"one");
+        assert_eq!(source.read_frame("slides/img2.png").unwrap(), b"two");
+        assert_eq!(source.read_frame("deck/title.png").unwrap(), b"title");
+        let err = source.read_frame("img2.png").unwrap_err();
+        assert!(err.to_string().contains("matches several entries"));
+        let err = source.read_frame("img3.png").unwrap_err();
+        assert!(err.to_string().contains("export/slides/img1.png"));
+        // The entries stay on the error when it's turned back into a `SlidevidError`.
+        let expected = [
+            "export/notes/img2.png",
+            "export/slides/img1.png",
+            "export/slides/img2.png",
+            "export/title.png",
+        ];
+        assert!(matches!(
+            SlidevidError::from(err),
+            SlidevidError::MissingFrame { name, available }
+                if name == "img3.png" && available == expected
+        ));
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn reads_plain_and_gzipped_tarballs() {
+        use std::io::Write;
+        let mut builder = tar::Builder::new(Vec::new());
+        let entries = [("0.png", &b"first"[..]), ("deck/1.png", &b"second"[..])];
+        for (name, data) in entries.iter() {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *data).unwrap();
+        }
+        let tarball = builder.into_inner().unwrap();
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&tarball).unwrap();
+        let gzipped = gzip.finish().unwrap();
+        for archive in [tarball, gzipped].iter() {
+            let mut source = TarSource::new(archive.as_slice()).unwrap();
+            assert_eq!(source.read_frame("0.png").unwrap(), b"first");
+            assert_eq!(source.read_frame("deck/1.png").unwrap(), b"second");
+            let err = source.read_frame("1.png").unwrap_err();
+            assert!(matches!(
+                err.downcast_ref(),
+                Some(SlidevidError::MissingFrame { .. })
+            ));
+        }
+        let empty = tar::Builder::new(Vec::new()).into_inner().unwrap();
+        let err = TarSource::new(empty.as_slice()).err().unwrap();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(SlidevidError::EmptyArchive)
+        ));
+    }
+}
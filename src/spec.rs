@@ -0,0 +1,84 @@
+use crate::{
+    configure_encoder, create_scaler, decode_slide, find_encoder, timing, transform::Transformer,
+    ConvertOptions, EncodeStats, Frame, FrameSource, Pass, PendingSlide, PixelFormat, RateControl,
+};
+use anyhow::Result;
+use ffmpeg::{codec, Rational};
+use std::{path::PathBuf, time::Duration};
+
+/// What an encode would produce, as worked out by a [`ConvertOptions::dry_run`].
+///
+/// [`ConvertOptions::dry_run`]: crate::ConvertOptions::dry_run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EncodeSpec {
+    pub width: u32,
+    pub height: u32,
+    pub codec: codec::Id,
+    pub pixel_format: PixelFormat,
+    pub time_base: Rational,
+    pub frame_count: usize,
+    pub total_duration: Duration,
+    /// Only known up front for bitrate based rate control.
+    pub estimated_size: Option<u64>,
+}
+
+/// Reads and decodes every slide and opens an encoder with the resulting settings, without
+/// encoding or writing anything. The returned stats have no output path.
+pub(crate) fn plan<F: FrameSource, S: AsRef<str>>(
+    source: &mut F,
+    frames: &[Frame<S>],
+    options: &ConvertOptions,
+) -> Result<EncodeStats> {
+    let frames = timing::select_frames(frames, options)?;
+    let delays = timing::resolve_delays(frames, options)?;
+    let time_base = timing::encoder_time_base(&delays, options)?;
+    let mut first = None;
+    let mut slide_sizes = Vec::with_capacity(frames.len());
+    for frame in frames {
+        let decoded = decode_slide(source, frame.filename.as_ref(), options)?;
+        slide_sizes.push((decoded.width(), decoded.height()));
+        first.get_or_insert(decoded);
+    }
+    // `encoder_time_base` succeeding means there is at least one frame.
+    let decoded = &mut first.unwrap();
+    let transformer = &mut Transformer::new(options);
+    let source = transformer.apply(decoded, &PendingSlide::default())?;
+    let (_, scaled) = create_scaler(source, options, None)?;
+    let (width, height) = (scaled.width(), scaled.height());
+    let codec = find_encoder(options)?;
+    let mut encoder = codec::Context::new().encoder().video()?;
+    let encoder_options = configure_encoder(
+        &mut encoder,
+        options,
+        Pass::Single,
+        (width, height),
+        time_base,
+    )?;
+    let encoder = encoder.open_as_with(codec, encoder_options)?;
+    let total_ms: u64 = delays.iter().map(|&d| d as u64).sum();
+    let estimated_size = match options.rate_control {
+        RateControl::Crf(_) | RateControl::Cqp(_) => None,
+        RateControl::Vbr { bitrate, .. }
+        | RateControl::Cbr(bitrate)
+        | RateControl::Abr(bitrate) => Some(bitrate as u64 * total_ms / 8_000),
+    };
+    let spec = EncodeSpec {
+        width,
+        height,
+        codec: options.codec_id(),
+        pixel_format: options.pixel_format,
+        time_base,
+        frame_count: frames.len(),
+        total_duration: Duration::from_millis(total_ms),
+        estimated_size,
+    };
+    Ok(EncodeStats {
+        output_path: PathBuf::new(),
+        codec_params: codec::Parameters::from(&encoder),
+        frame_timings: Vec::new(),
+        keyframes: Vec::new(),
+        timing_mode: timing::timing_mode(&delays, options),
+        slide_sizes,
+        spec: Some(spec),
+    })
+}
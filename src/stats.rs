@@ -0,0 +1,53 @@
+use crate::{EncodeSpec, TimingMode};
+use ffmpeg::codec;
+use std::{fmt, path::PathBuf, time::Duration};
+
+#[derive(Clone)]
+pub struct EncodeStats {
+    /// Where the output was written; empty for [`encode_to_stream`](crate::encode_to_stream).
+    pub output_path: PathBuf,
+    pub codec_params: codec::Parameters,
+    /// Per-frame phase timings, only recorded when [`ConvertOptions::benchmark`] is set.
+    ///
+    /// [`ConvertOptions::benchmark`]: crate::ConvertOptions::benchmark
+    pub frame_timings: Vec<FrameTiming>,
+    /// Width and height of each slide as decoded, before any crop, transform or scaling.
+    pub slide_sizes: Vec<(u32, u32)>,
+    pub timing_mode: TimingMode,
+    /// When each keyframe of the output is shown, leaving out the repeat of the last slide for
+    /// [`ConvertOptions::end_hold_ms`]. With [`ConvertOptions::keyframe_per_slide`] set, entry
+    /// `i` is where the slide of `slide_sizes[i]` starts: bad leading slides that were skipped
+    /// have no entry, and slides [`ConvertOptions::max_fps`] pushed back start where they were
+    /// pushed to.
+    ///
+    /// [`ConvertOptions::end_hold_ms`]: crate::ConvertOptions::end_hold_ms
+    /// [`ConvertOptions::keyframe_per_slide`]: crate::ConvertOptions::keyframe_per_slide
+    /// [`ConvertOptions::max_fps`]: crate::ConvertOptions::max_fps
+    pub keyframes: Vec<Duration>,
+    /// The planned output, only set for a [`ConvertOptions::dry_run`], which encodes nothing.
+    ///
+    /// [`ConvertOptions::dry_run`]: crate::ConvertOptions::dry_run
+    pub spec: Option<EncodeSpec>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameTiming {
+    pub send_packet: Duration,
+    pub receive_frame: Duration,
+    pub scale: Duration,
+    pub encode: Duration,
+}
+
+impl fmt::Debug for EncodeStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EncodeStats")
+            .field("output_path", &self.output_path)
+            .field("codec_params", &self.codec_params.id())
+            .field("frame_timings", &self.frame_timings)
+            .field("slide_sizes", &self.slide_sizes)
+            .field("timing_mode", &self.timing_mode)
+            .field("keyframes", &self.keyframes)
+            .field("spec", &self.spec)
+            .finish()
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    color, configure_encoder, create_scaler, find_encoder, image_packet, open_decoder_as,
+    send_frame, timing, transform::Transformer, wrap_result, ConvertOptions, FrameTiming, Pass,
+    PendingSlide, Scaled, MILLIS,
+};
+use anyhow::{anyhow, Result};
+use ffmpeg::{codec, encoder, frame, software::scaling, Packet, Rational, Rescale};
+use std::mem;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Encodes slides pushed one at a time, handing back the compressed packets instead of muxing
+/// them. Packets are timestamped in [`SlideEncoder::time_base`].
+pub struct SlideEncoder {
+    options: ConvertOptions,
+    transformer: Transformer,
+    state: Option<State>,
+    timestamp: i64,
+    /// Length of one tick of the time base in milliseconds.
+    tick_ms: u32,
+}
+
+struct State {
+    encoder: encoder::video::Encoder,
+    scaler: scaling::Context,
+    scaled: Scaled,
+}
+
+impl SlideEncoder {
+    pub fn new(options: ConvertOptions) -> Self {
+        Self {
+            transformer: Transformer::new(&options),
+            options,
+            state: None,
+            timestamp: 0,
+            tick_ms: timing::MIN_DELAY_MS,
+        }
+    }
+
+    /// Like [`new`](Self::new), but with a time base of `tick_ms` milliseconds rather than the
+    /// shortest supported delay. The encoder takes the tick as its frame rate, so decks whose
+    /// slides all last whole seconds can use a tick of 1000 to keep H.264's level low. Delays are
+    /// floored at one tick and rounded to whole ticks.
+    pub fn with_tick_ms(options: ConvertOptions, tick_ms: u32) -> Result<Self> {
+        if tick_ms < timing::MIN_DELAY_MS {
+            return Err(anyhow!(
+                "Tick of {}ms is below the minimum supported {}ms",
+                tick_ms,
+                timing::MIN_DELAY_MS
+            ));
+        }
+        if tick_ms > i32::MAX as u32 {
+            return Err(anyhow!("Tick of {}ms is too long", tick_ms));
+        }
+        Ok(Self {
+            tick_ms,
+            ..Self::new(options)
+        })
+    }
+
+    pub fn time_base(&self) -> Rational {
+        Rational(self.tick_ms as i32, MILLIS)
+    }
+
+    /// The encoder's codec parameters, available once the first frame has been pushed.
+    pub fn parameters(&self) -> Option<codec::Parameters> {
+        self.state
+            .as_ref()
+            .map(|state| codec::Parameters::from(&state.encoder))
+    }
+
+    /// Decodes a PNG or JPEG image and encodes it to be shown for `delay` milliseconds.
+    pub fn push_frame(&mut self, image: &[u8], delay: u32) -> Result<Vec<Packet>> {
+        let id = match self.options.force_input_codec {
+            Some(id) => id,
+            None if image.starts_with(PNG_SIGNATURE) => codec::Id::PNG,
+            None => codec::Id::MJPEG,
+        };
+        let mut decoder = open_decoder_as(id)?;
+        let mut packet = image_packet(image);
+        let delay = delay.max(self.tick_ms);
+        let decoder_time_base = self.options.decoder_time_base;
+        timing::check_decoder_time_base(decoder_time_base, delay)?;
+        let duration = (delay as i64).rescale(Rational(1, MILLIS), decoder_time_base);
+        packet.set_pts(Some(self.timestamp));
+        packet.set_duration(duration);
+        packet.rescale_ts(decoder_time_base, self.time_base());
+        self.timestamp += duration;
+        decoder.send_packet(&packet)?;
+        decoder.send_eof()?;
+        let decoded = &mut frame::Video::empty();
+        decoder.receive_frame(decoded)?;
+
+        let time_base = self.time_base();
+        let source = self.transformer.apply(decoded, &PendingSlide::default())?;
+        if self.state.is_none() {
+            self.options = color::resolve(&self.options, source).into_owned();
+        }
+        let options = &self.options;
+        let state = match &mut self.state {
+            Some(state) => state,
+            state => state.insert(State::new(source, options, time_base)?),
+        };
+        send_frame(
+            &mut state.encoder,
+            source,
+            &mut state.scaler,
+            &mut state.scaled,
+            None,
+            options,
+            &mut FrameTiming::default(),
+        )?;
+        state.receive_packets()
+    }
+
+    /// Flushes the encoder, returning the remaining packets.
+    pub fn finish(mut self) -> Result<Vec<Packet>> {
+        match &mut self.state {
+            Some(state) => {
+                state.encoder.send_eof()?;
+                state.receive_packets()
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+impl State {
+    fn new(source: &frame::Video, options: &ConvertOptions, time_base: Rational) -> Result<Self> {
+        let (scaler, scaled) = create_scaler(source, options, None)?;
+        let codec = find_encoder(options)?;
+        let mut encoder = codec::Context::new().encoder().video()?;
+        let encoder_options = configure_encoder(
+            &mut encoder,
+            options,
+            Pass::Single,
+            (scaled.width(), scaled.height()),
+            time_base,
+        )?;
+        Ok(Self {
+            encoder: encoder.open_as_with(codec, encoder_options)?,
+            scaler,
+            scaled: Scaled::new(scaled),
+        })
+    }
+
+    fn receive_packets(&mut self) -> Result<Vec<Packet>> {
+        let mut packets = Vec::new();
+        let mut packet = Packet::empty();
+        while wrap_result(self.encoder.receive_packet(&mut packet))? {
+            packets.push(mem::replace(&mut packet, Packet::empty()));
+        }
+        Ok(packets)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{transform, Rgb};
+use anyhow::Result;
+use ffmpeg::frame;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SubtitlePosition {
+    Top,
+    #[default]
+    Bottom,
+}
+
+/// How [`Frame::with_subtitle`](crate::Frame::with_subtitle) text is drawn onto its slide.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubtitleStyle {
+    /// Font to draw with; `None` asks fontconfig for its default sans-serif font.
+    pub font_file: Option<PathBuf>,
+    /// Font size in pixels of the decoded slide.
+    pub font_size: u32,
+    pub color: Rgb,
+    pub position: SubtitlePosition,
+    /// Color of a box drawn behind the text to keep it legible, if any.
+    pub background: Option<Rgb>,
+}
+
+impl Default for SubtitleStyle {
+    fn default() -> Self {
+        Self {
+            font_file: None,
+            font_size: 32,
+            color: Rgb::default(),
+            position: SubtitlePosition::default(),
+            background: Some(Rgb([0; 3])),
+        }
+    }
+}
+
+impl SubtitleStyle {
+    fn filter_spec(&self, text: &str) -> String {
+        let margin = self.font_size;
+        let y = match self.position {
+            SubtitlePosition::Top => format!("{}", margin),
+            SubtitlePosition::Bottom => format!("h-text_h-{}", margin),
+        };
+        let mut spec = format!(
+            "drawtext=expansion=none:text={}:fontsize={}:fontcolor={}:x=(w-text_w)/2:y={}",
+            escape(text),
+            self.font_size,
+            color(self.color),
+            y
+        );
+        match &self.font_file {
+            Some(font_file) => {
+                spec += &format!(":fontfile={}", escape(&font_file.to_string_lossy()))
+            }
+            None => spec += ":font=Sans",
+        }
+        if let Some(background) = self.background {
+            spec += &format!(
+                ":box=1:boxcolor={}@0.6:boxborderw={}",
+                color(background),
+                margin / 4
+            );
+        }
+        spec
+    }
+}
+
+fn color(Rgb([r, g, b]): Rgb) -> String {
+    format!("0x{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// Option values are escaped once for the option parser and again for the filtergraph parser.
+fn escape(value: &str) -> String {
+    let mut option = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '\'' | ':') {
+            option.push('\\');
+        }
+        option.push(c);
+    }
+    let mut graph = String::with_capacity(option.len());
+    for c in option.chars() {
+        if matches!(c, '\\' | '\'' | '[' | ']' | ',' | ';') {
+            graph.push('\\');
+        }
+        graph.push(c);
+    }
+    graph
+}
+
+/// Burns subtitle text onto frames, with the filter graph rebuilt whenever the text or input
+/// changes.
+pub(crate) struct Subtitler {
+    style: SubtitleStyle,
+    graph: Option<(transform::Graph, String)>,
+}
+
+impl Subtitler {
+    pub(crate) fn new(style: SubtitleStyle) -> Self {
+        Self { style, graph: None }
+    }
+
+    pub(crate) fn apply<'a>(
+        &'a mut self,
+        source: &'a frame::Video,
+        text: Option<&str>,
+    ) -> Result<&'a frame::Video> {
+        let text = match text {
+            Some(text) if !text.is_empty() => text,
+            _ => return Ok(source),
+        };
+        let input = (source.format(), source.width(), source.height());
+        let current = self
+            .graph
+            .as_ref()
+            .map(|(graph, text)| (graph.input(), text.as_str()));
+        if current != Some((input, text)) {
+            let graph = transform::Graph::new(&self.style.filter_spec(text), input)?;
+            self.graph = Some((graph, text.to_owned()));
+        }
+        self.graph.as_mut().unwrap().0.run(source)
+    }
+}
@@ -0,0 +1,262 @@
+//! Fixtures for the tests, which generate their slides instead of checking in binary files.
+
+use crate::{wrap_result, Frame};
+use ffmpeg::{codec, decoder, format, frame, media, Packet, Rational};
+use std::{
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+/// A zip with a `width`x`height` gradient per entry of `frames`, named `0.png`, `1.jpg`, ... by
+/// index and format, which is either `"png"` or `"jpg"`.
+pub(crate) fn make_test_zip(frames: &[(u32, u32, &str)]) -> Vec<u8> {
+    let entries: Vec<_> = frames
+        .iter()
+        .enumerate()
+        .map(|(index, &(width, height, format))| {
+            (
+                format!("{}.{}", index, format),
+                make_image(width, height, format),
+            )
+        })
+        .collect();
+    make_zip(&entries)
+}
+
+/// The frames of a zip made by [`make_test_zip`] from `frames`, each lasting `delay`.
+pub(crate) fn test_frames(frames: &[(u32, u32, &str)], delay: u32) -> Vec<Frame<String>> {
+    frames
+        .iter()
+        .enumerate()
+        .map(|(index, &(_, _, format))| Frame::new(format!("{}.{}", index, format), delay))
+        .collect()
+}
+
+/// A `width`x`height` gradient, encoded as `"png"` or `"jpg"`.
+pub(crate) fn make_image(width: u32, height: u32, format: &str) -> Vec<u8> {
+    let image = image::RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([
+            (x * 255 / width.max(1)) as u8,
+            (y * 255 / height.max(1)) as u8,
+            128,
+        ])
+    });
+    let format = match format {
+        "png" => image::ImageOutputFormat::Png,
+        "jpg" | "jpeg" => image::ImageOutputFormat::Jpeg(90),
+        format => panic!("Can't generate {:?} images", format),
+    };
+    let mut data = Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgb8(image)
+        .write_to(&mut data, format)
+        .unwrap();
+    data.into_inner()
+}
+
+/// A `width`x`height` gradient encoded as a JPEG whose EXIF data says to display it with
+/// `orientation`, as phone cameras tag photos taken sideways.
+pub(crate) fn make_oriented_jpeg(width: u32, height: u32, orientation: u16) -> Vec<u8> {
+    let jpeg = make_image(width, height, "jpg");
+    // A little-endian TIFF header and a single IFD holding just the orientation, a SHORT.
+    let mut exif = b"Exif\0\0II\x2a\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0".to_vec();
+    exif.extend_from_slice(&orientation.to_le_bytes());
+    exif.extend_from_slice(&[0; 6]);
+    let mut data = jpeg[..2].to_vec();
+    data.extend_from_slice(&[0xff, 0xe1]);
+    data.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+    data.extend_from_slice(&exif);
+    data.extend_from_slice(&jpeg[2..]);
+    data
+}
+
+/// A zip storing each of `entries` uncompressed under its name.
+pub(crate) fn make_zip<N: AsRef<str>, D: AsRef<[u8]>>(entries: &[(N, D)]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    for (name, data) in entries {
+        zip.start_file(name.as_ref(), options).unwrap();
+        zip.write_all(data.as_ref()).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+/// A zip storing each of `entries` uncompressed and encrypted with `password`, using the
+/// traditional PKWARE cipher that the zip crate reads but can't write.
+pub(crate) fn make_encrypted_zip<N: AsRef<str>, D: AsRef<[u8]>>(
+    entries: &[(N, D)],
+    password: &[u8],
+) -> Vec<u8> {
+    let (mut zip, mut directory) = (Vec::new(), Vec::new());
+    for (name, data) in entries {
+        let (name, data) = (name.as_ref().as_bytes(), data.as_ref());
+        let crc = !data.iter().fold(!0, |crc, &byte| crc32(crc, byte));
+        // The encryption header is padding, except for its last byte, which checks the password.
+        let mut header = [0; 12];
+        header[11] = (crc >> 24) as u8;
+        let mut cipher = ZipCrypto::new(password);
+        let encrypted: Vec<u8> = header
+            .iter()
+            .chain(data)
+            .map(|&byte| cipher.encrypt(byte))
+            .collect();
+        // From the version needed to extract through the extra field length, in both headers:
+        // encrypted, stored, and dated 1980-01-01.
+        let mut fields = Vec::new();
+        for value in [20u16, 1, 0, 0, 0x21].iter() {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [crc, encrypted.len() as u32, data.len() as u32].iter() {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&fields);
+        // No comment, on the first disk, with no attributes.
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&(zip.len() as u32).to_le_bytes());
+        directory.extend_from_slice(name);
+        zip.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        zip.extend_from_slice(&fields);
+        zip.extend_from_slice(name);
+        zip.extend_from_slice(&encrypted);
+    }
+    let offset = zip.len() as u32;
+    zip.extend_from_slice(&directory);
+    zip.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    zip.extend_from_slice(&[0; 4]);
+    for _ in 0..2 {
+        zip.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    }
+    zip.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    zip.extend_from_slice(&offset.to_le_bytes());
+    zip.extend_from_slice(&0u16.to_le_bytes());
+    zip
+}
+
+/// One byte's step of CRC-32, without the initial and final inversions.
+fn crc32(crc: u32, byte: u8) -> u32 {
+    let mut value = (crc ^ byte as u32) & 0xff;
+    for _ in 0..8 {
+        value = if value & 1 == 1 {
+            0xedb8_8320 ^ (value >> 1)
+        } else {
+            value >> 1
+        };
+    }
+    (crc >> 8) ^ value
+}
+
+/// The traditional PKWARE stream cipher, keyed by the password.
+struct ZipCrypto([u32; 3]);
+
+impl ZipCrypto {
+    fn new(password: &[u8]) -> Self {
+        let mut cipher = Self([0x1234_5678, 0x2345_6789, 0x3456_7890]);
+        for &byte in password {
+            cipher.update(byte);
+        }
+        cipher
+    }
+
+    fn update(&mut self, byte: u8) {
+        let keys = &mut self.0;
+        keys[0] = crc32(keys[0], byte);
+        keys[1] = keys[1]
+            .wrapping_add(keys[0] & 0xff)
+            .wrapping_mul(0x0808_8405)
+            .wrapping_add(1);
+        keys[2] = crc32(keys[2], (keys[1] >> 24) as u8);
+    }
+
+    fn encrypt(&mut self, byte: u8) -> u8 {
+        let temp = self.0[2] as u16 | 2;
+        let encrypted = byte ^ (temp.wrapping_mul(temp ^ 1) >> 8) as u8;
+        self.update(byte);
+        encrypted
+    }
+}
+
+/// A directory under the system's temporary directory, removed with everything in it on drop.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "slidevid-test-{}-{}",
+            process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub(crate) fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The video stream of an encoded file, read back for the tests to check.
+pub(crate) struct Probe {
+    pub(crate) decoder: decoder::Video,
+    /// The stream's packets in decoding order.
+    pub(crate) packets: Vec<Packet>,
+    /// What the packets' timestamps count.
+    pub(crate) time_base: Rational,
+    /// Duration of the whole file in milliseconds.
+    pub(crate) duration_ms: i64,
+}
+
+impl Probe {
+    pub(crate) fn new(path: &Path) -> Self {
+        ffmpeg::init().unwrap();
+        let mut input = format::input(&path).unwrap();
+        let stream = input.streams().best(media::Type::Video).unwrap();
+        let (index, time_base) = (stream.index(), stream.time_base());
+        let decoder = codec::Context::from_parameters(stream.parameters())
+            .unwrap()
+            .decoder()
+            .video()
+            .unwrap();
+        let duration_ms = input.duration() / 1_000;
+        let packets = input
+            .packets()
+            .filter(|(stream, _)| stream.index() == index)
+            .map(|(_, packet)| packet)
+            .collect();
+        Self {
+            decoder,
+            packets,
+            time_base,
+            duration_ms,
+        }
+    }
+
+    /// Decodes every frame of the stream.
+    pub(crate) fn frames(&mut self) -> Vec<frame::Video> {
+        let mut frames = Vec::new();
+        let mut decoded = frame::Video::empty();
+        for packet in &self.packets {
+            self.decoder.send_packet(packet).unwrap();
+            while wrap_result(self.decoder.receive_frame(&mut decoded)).unwrap() {
+                frames.push(decoded.clone());
+            }
+        }
+        self.decoder.send_eof().unwrap();
+        while wrap_result(self.decoder.receive_frame(&mut decoded)).unwrap() {
+            frames.push(decoded.clone());
+        }
+        frames
+    }
+}
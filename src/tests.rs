@@ -0,0 +1,859 @@
+use crate::test_util::{self, make_test_zip, make_zip, test_frames, Probe, TempDir};
+use crate::*;
+use ffmpeg::Rational;
+
+#[test]
+fn converts_png_and_jpeg_slides() {
+    let slides = [(320, 240, "png"), (320, 240, "jpg"), (320, 240, "png")];
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let stats =
+        convert_to_mp4(make_test_zip(&slides), &test_frames(&slides, 500), &output).unwrap();
+    assert_eq!(stats.output_path, output);
+    assert_eq!(stats.slide_sizes, vec![(320, 240); 3]);
+    assert_eq!(stats.timing_mode, TimingMode::Constant(Rational(2, 1)));
+    let mut probe = Probe::new(&output);
+    assert_eq!((probe.decoder.width(), probe.decoder.height()), (320, 240));
+    assert_eq!(probe.frames().len(), 3);
+    assert!((probe.duration_ms - 1_500).abs() <= 10);
+}
+
+#[test]
+fn fails_on_a_missing_frame() {
+    let slides = [(64, 64, "png")];
+    let frames = [Frame::new("0.png", 100), Frame::new("1.png", 100)];
+    let dir = TempDir::new();
+    let err = convert_to_mp4(make_test_zip(&slides), &frames, dir.join("out.mp4")).unwrap_err();
+    assert!(matches!(err, SlidevidError::MissingFrame { name, .. } if name == "1.png"));
+}
+
+#[test]
+fn rejects_an_empty_archive() {
+    let dir = TempDir::new();
+    let entries: [(&str, &[u8]); 0] = [];
+    let err = convert_to_mp4(
+        make_zip(&entries),
+        &[Frame::new("0.png", 100)],
+        dir.join("a.mp4"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, SlidevidError::EmptyArchive));
+}
+
+#[test]
+fn validates_frames() {
+    assert!(Frame::new("slide.png", 100).validate().is_ok());
+    assert!(Frame::without_delay("slide.png").validate().is_ok());
+    assert!(Frame::new("", 100).validate().is_err());
+    assert!(Frame::new("sli\0de.png", 100).validate().is_err());
+    assert!(Frame::new("a".repeat(u16::MAX as usize + 1), 100)
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn rejects_a_zero_delay_by_index() {
+    let frames = [Frame::new("0.png", 100), Frame::new("1.png", 0)];
+    let dir = TempDir::new();
+    let zip = make_zip(&[("0.png", b"")]);
+    let err = convert_to_mp4(zip, &frames, dir.join("out.mp4")).unwrap_err();
+    assert!(matches!(
+        err,
+        SlidevidError::InvalidDelay { frame: 1, delay: 0 }
+    ));
+}
+
+#[test]
+fn cancelling_before_the_first_slide_leaves_no_output() {
+    let slides = [(64, 64, "png"), (64, 64, "png")];
+    let token = CancellationToken::new();
+    token.cancel();
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let options = ConvertOptions::new().cancellation_token(token);
+    let err = convert(
+        make_test_zip(&slides),
+        &test_frames(&slides, 100),
+        &output,
+        &options,
+    )
+    .unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(SlidevidError::Cancelled)));
+    assert!(!output.exists());
+}
+
+#[test]
+fn skips_a_corrupt_leading_slide() {
+    let zip = make_zip(&[
+        ("0.png", b"not a png".to_vec()),
+        ("1.png", test_util::make_image(64, 64, "png")),
+        ("2.png", test_util::make_image(64, 64, "png")),
+    ]);
+    let frames = [
+        Frame::new("0.png", 500).with_chapter_title("Broken"),
+        Frame::new("1.png", 100),
+        Frame::new("2.png", 100),
+    ];
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let options = ConvertOptions::new().skip_bad_leading_slides(true);
+    let stats = convert(zip, &frames, &output, &options).unwrap();
+    assert_eq!(stats.slide_sizes.len(), 2);
+    assert_eq!(stats.timing_mode, TimingMode::Constant(Rational(10, 1)));
+    let mut probe = Probe::new(&output);
+    assert_eq!(probe.frames().len(), 2);
+    assert!((probe.duration_ms - 200).abs() <= 10);
+    let input = ffmpeg::format::input(&output).unwrap();
+    assert_eq!(input.nb_chapters(), 0);
+}
+
+#[test]
+fn convert_to_mp4_errors_are_typed() {
+    let dir = TempDir::new();
+    let err = convert_to_mp4(
+        b"not a zip",
+        &[Frame::new("0.png", 100)],
+        dir.join("out.mp4"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, SlidevidError::Other(_)));
+    let err = SlidevidError::from(anyhow::Error::new(std::io::Error::from(
+        std::io::ErrorKind::NotFound,
+    )));
+    assert!(matches!(err, SlidevidError::IoError(_)));
+}
+
+#[test]
+fn concat_checks_sizes_with_the_conversion_options() {
+    let image = test_util::make_image(64, 48, "png");
+    let nested = make_zip(&[("deck/0.png", &image)]);
+    let inputs = vec![
+        (nested.clone(), vec![Frame::without_delay("0.png")]),
+        (nested, vec![Frame::without_delay("0.png")]),
+    ];
+    let dir = TempDir::new();
+    let options = ConvertOptions::new()
+        .loose_entry_names(true)
+        .default_delay_ms(100);
+    let stats = concat_videos(inputs, dir.join("out.mp4"), &options).unwrap();
+    assert_eq!(stats.slide_sizes, vec![(64, 48); 2]);
+}
+
+#[test]
+fn rejects_mixed_resolutions_in_the_selected_range() {
+    let slides = [(320, 240, "png"), (320, 240, "jpg"), (640, 480, "png")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let options = ConvertOptions::new().reject_mixed_resolution(true);
+    let err = convert(&zip, &frames, dir.join("all.mp4"), &options).unwrap_err();
+    assert!(err.to_string().contains("2.png (640x480)"));
+    let options = options.frame_range(0, 2);
+    convert(&zip, &frames, dir.join("range.mp4"), &options).unwrap();
+}
+
+#[test]
+fn converts_to_a_gif() {
+    let slides = [(64, 48, "png"), (64, 48, "jpg")];
+    let dir = TempDir::new();
+    let output = dir.join("out.gif");
+    let options = ConvertOptions::new().container(Container::Gif);
+    convert(
+        make_test_zip(&slides),
+        &test_frames(&slides, 500),
+        &output,
+        &options,
+    )
+    .unwrap();
+    let mut probe = Probe::new(&output);
+    assert_eq!(probe.decoder.id(), ffmpeg::codec::Id::GIF);
+    assert_eq!(probe.frames().len(), 2);
+}
+
+#[test]
+fn adds_a_silent_track_as_long_as_the_video() {
+    let slides = [(64, 48, "png"), (64, 48, "png")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 700));
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    convert(
+        &zip,
+        &frames,
+        &output,
+        &ConvertOptions::new().silent_audio(true),
+    )
+    .unwrap();
+    let input = ffmpeg::format::input(&output).unwrap();
+    let audio = input.streams().best(ffmpeg::media::Type::Audio).unwrap();
+    assert_eq!(audio.parameters().id(), ffmpeg::codec::Id::AAC);
+    let duration = audio
+        .duration()
+        .rescale(audio.time_base(), Rational(1, 1_000));
+    assert!((duration - 1_400).abs() <= 25);
+    let options = ConvertOptions::new()
+        .container(Container::WebM)
+        .silent_audio(true);
+    assert!(convert(&zip, &frames, dir.join("out.webm"), &options).is_err());
+}
+
+#[test]
+fn keeps_the_slides_colors_unless_set() {
+    use ffmpeg::color;
+    let slides = [(64, 48, "jpg")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let output = dir.join("jpeg.mp4");
+    convert(&zip, &frames, &output, &ConvertOptions::new()).unwrap();
+    let probe = Probe::new(&output);
+    assert_eq!(probe.decoder.color_space(), color::Space::SMPTE170M);
+    assert_eq!(probe.decoder.color_range(), color::Range::JPEG);
+    let output = dir.join("hdr.mp4");
+    let options = ConvertOptions::new()
+        .pixel_format(PixelFormat::Yuv420p10le)
+        .colorspace(ColorSpace::Bt2020Ncl)
+        .color_range(ColorRange::Tv)
+        .color_primaries(ColorPrimaries::Bt2020)
+        .color_trc(ColorTrc::Smpte2084);
+    convert(&zip, &frames, &output, &options).unwrap();
+    let probe = Probe::new(&output);
+    assert_eq!(probe.decoder.color_space(), color::Space::BT2020NCL);
+    assert_eq!(probe.decoder.color_range(), color::Range::MPEG);
+}
+
+#[test]
+fn scales_a_slide_to_its_own_size() {
+    let slides = [(320, 240, "png"), (320, 240, "png")];
+    let mut frames = test_frames(&slides, 100);
+    frames[1] = frames[1]
+        .clone()
+        .with_size(160, 120)
+        .with_scaling(ScaleAlgorithm::Nearest);
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let stats = convert(
+        make_test_zip(&slides),
+        &frames,
+        &output,
+        &ConvertOptions::new(),
+    )
+    .unwrap();
+    assert_eq!(stats.slide_sizes, vec![(320, 240); 2]);
+    let mut probe = Probe::new(&output);
+    assert_eq!((probe.decoder.width(), probe.decoder.height()), (320, 240));
+    // The gradient is brightest in the bottom right corner, which the smaller slide leaves to
+    // the black letterbox fill.
+    let corner = |frame: &frame::Video| frame.data(0)[frame.stride(0) * 239 + 319];
+    let decoded = probe.frames();
+    assert!(corner(&decoded[0]) > 200);
+    assert!(corner(&decoded[1]) < 30);
+    frames[1] = Frame::new("1.png".to_owned(), 100).with_size(640, 480);
+    let err = convert(
+        make_test_zip(&slides),
+        &frames,
+        dir.join("big.mp4"),
+        &ConvertOptions::new(),
+    );
+    assert!(err.unwrap_err().to_string().contains("doesn't fit"));
+}
+
+#[test]
+fn splits_the_selected_range_into_chapters() {
+    let slides = [(64, 48, "png"); 4];
+    let mut frames = test_frames(&slides, 100);
+    frames[1] = frames[1].clone().with_chapter_title("One");
+    frames[3] = frames[3].clone().with_chapter_title("Two");
+    let dir = TempDir::new();
+    let output_dir = dir.join("chapters");
+    let options = ConvertOptions::new()
+        .frame_range(1, 4)
+        .write_mode(WriteMode::AppendTimestamp);
+    let paths = split_video(make_test_zip(&slides), &frames, &output_dir, &options).unwrap();
+    assert_eq!(paths.len(), 2);
+    assert!(paths[0].to_str().unwrap().contains("chapter_01_"));
+    assert!(paths[1].to_str().unwrap().contains("chapter_02_"));
+    assert_eq!(Probe::new(&paths[0]).frames().len(), 2);
+    assert_eq!(Probe::new(&paths[1]).frames().len(), 1);
+    assert_eq!(std::fs::read_dir(&output_dir).unwrap().count(), 2);
+}
+
+#[test]
+fn sharpens_only_with_a_valid_strength() {
+    let slides = [(64, 48, "png"), (64, 48, "jpg")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let options = ConvertOptions::new().sharpen(1.0);
+    convert(&zip, &frames, dir.join("sharp.mp4"), &options).unwrap();
+    for strength in [-0.5, f32::NAN].iter() {
+        let options = ConvertOptions::new().sharpen(*strength);
+        let err = convert(&zip, &frames, dir.join("bad.mp4"), &options).unwrap_err();
+        assert!(err.to_string().contains("Sharpening strength"));
+    }
+}
+
+#[test]
+fn streams_slides_in_the_callers_tick() {
+    assert!(SlideEncoder::with_tick_ms(ConvertOptions::new(), 5).is_err());
+    let mut encoder = SlideEncoder::with_tick_ms(ConvertOptions::new(), 1_000).unwrap();
+    assert_eq!(encoder.time_base(), Rational(1_000, 1_000));
+    let image = test_util::make_image(64, 48, "png");
+    let mut packets = encoder.push_frame(&image, 2_000).unwrap();
+    packets.extend(encoder.push_frame(&image, 400).unwrap());
+    packets.extend(encoder.finish().unwrap());
+    let mut pts: Vec<_> = packets.iter().filter_map(|packet| packet.pts()).collect();
+    pts.sort_unstable();
+    assert_eq!(pts, [0, 2]);
+}
+
+#[test]
+fn converts_frames_timed_by_timestamps() {
+    let slides = [(64, 48, "png"), (64, 48, "png"), (64, 48, "png")];
+    let starts = [
+        Duration::ZERO,
+        Duration::from_millis(400),
+        Duration::from_millis(1_000),
+    ];
+    let names = test_frames(&slides, 0)
+        .into_iter()
+        .map(|frame| frame.filename)
+        .collect();
+    let frames = frames_from_timestamps(names, &starts, Duration::from_millis(1_600)).unwrap();
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let stats = convert(
+        make_test_zip(&slides),
+        &frames,
+        &output,
+        &ConvertOptions::new(),
+    )
+    .unwrap();
+    assert_eq!(stats.timing_mode, TimingMode::Variable);
+    let probe = Probe::new(&output);
+    assert!((probe.duration_ms - 1_600).abs() <= 10);
+    assert_eq!(probe.packets.len(), 3);
+}
+
+#[test]
+fn encodes_at_a_constant_quantizer() {
+    let slides = [(320, 240, "png"), (320, 240, "jpg")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let size = |qp| {
+        let output = dir.join(&format!("qp{}.mp4", qp));
+        let options = ConvertOptions::new().rate_control(RateControl::Cqp(qp));
+        convert(&zip, &frames, &output, &options).unwrap();
+        std::fs::metadata(&output).unwrap().len()
+    };
+    assert!(size(10) > size(45));
+    let options = ConvertOptions::new().rate_control(RateControl::Cqp(52));
+    assert!(convert(&zip, &frames, dir.join("bad.mp4"), &options).is_err());
+}
+
+#[test]
+fn encodes_close_to_a_constant_bitrate() {
+    let slides = [
+        (320, 240, "png"),
+        (320, 240, "jpg"),
+        (320, 240, "png"),
+        (320, 240, "jpg"),
+    ];
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let options = ConvertOptions::new().rate_control(RateControl::Cbr(500_000));
+    convert(
+        make_test_zip(&slides),
+        &test_frames(&slides, 1_000),
+        &output,
+        &options,
+    )
+    .unwrap();
+    // Four seconds at 500kbps, with x264's filler data padding the static slides out to it.
+    let budget = 4 * 500_000 / 8;
+    let size = std::fs::metadata(&output).unwrap().len();
+    assert!(size > budget / 2 && size < budget * 3 / 2, "{} bytes", size);
+}
+
+#[test]
+fn spaces_keyframes_by_the_gop_size() {
+    // The slides are identical, so x264 has no scene cuts to add keyframes at.
+    let slides = [(64, 48, "png"); 7];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let keyframes = |options: &ConvertOptions| {
+        let output = dir.join("out.mp4");
+        convert(&zip, &frames, &output, options).unwrap();
+        let probe = Probe::new(&output);
+        let mut pts: Vec<_> = probe
+            .packets
+            .iter()
+            .filter(|packet| packet.is_key())
+            .filter_map(Packet::pts)
+            .collect();
+        pts.sort_unstable();
+        pts
+    };
+    assert_eq!(keyframes(&ConvertOptions::new()).len(), 1);
+    let pts = keyframes(&ConvertOptions::new().gop_size(3));
+    assert_eq!(pts.len(), 3);
+    assert_eq!(pts[1] - pts[0], pts[2] - pts[1]);
+}
+
+#[test]
+fn starts_every_slide_on_a_keyframe() {
+    let slides = [(64, 48, "png"); 4];
+    let zip = make_test_zip(&slides);
+    let frames: Vec<_> = [300, 500, 200, 1_000]
+        .iter()
+        .enumerate()
+        .map(|(index, &delay)| Frame::new(format!("{}.png", index), delay))
+        .collect();
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let options = ConvertOptions::new()
+        .keyframe_per_slide(true)
+        .end_hold_ms(500);
+    let stats = convert(&zip, &frames, &output, &options).unwrap();
+    let starts = [0i64, 300, 800, 1_000];
+    let expected: Vec<_> = starts
+        .iter()
+        .map(|&ms| Duration::from_millis(ms as u64))
+        .collect();
+    assert_eq!(stats.keyframes, expected);
+    let probe = Probe::new(&output);
+    let mut keyframes: Vec<_> = probe
+        .packets
+        .iter()
+        .filter(|packet| packet.is_key())
+        .filter_map(Packet::pts)
+        .map(|pts| pts.rescale(probe.time_base, Rational(1, 1_000)))
+        .collect();
+    keyframes.sort_unstable();
+    assert_eq!(keyframes, starts);
+    // The end hold's repeat is the only frame that isn't a keyframe.
+    assert_eq!(probe.packets.len(), starts.len() + 1);
+}
+
+#[test]
+fn encodes_with_the_chosen_profile_and_level() {
+    use ffmpeg::codec::profile::{Profile as CodecProfile, H264};
+    let slides = [(320, 240, "png"), (320, 240, "jpg")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let options = ConvertOptions::new()
+        .profile(Profile::Baseline)
+        .level(Level::L31);
+    convert(&zip, &frames, &output, &options).unwrap();
+    let probe = Probe::new(&output);
+    assert!(matches!(
+        probe.decoder.profile(),
+        CodecProfile::H264(H264::Baseline) | CodecProfile::H264(H264::ConstrainedBaseline)
+    ));
+    assert_eq!(unsafe { (*probe.decoder.as_ptr()).level }, 31);
+    let options = options.bframes(2);
+    assert!(convert(&zip, &frames, dir.join("bframes.mp4"), &options).is_err());
+}
+
+#[test]
+fn encodes_without_b_frames() {
+    let slides = [
+        (64, 48, "png"),
+        (64, 48, "jpg"),
+        (64, 48, "png"),
+        (64, 48, "jpg"),
+        (64, 48, "png"),
+    ];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    convert(&zip, &frames, &output, &ConvertOptions::new().bframes(0)).unwrap();
+    let mut probe = Probe::new(&output);
+    let decoded = probe.frames();
+    assert_eq!(decoded.len(), slides.len());
+    assert!(decoded
+        .iter()
+        .all(|frame| frame.kind() != ffmpeg::picture::Type::B));
+}
+
+#[test]
+fn writes_a_bare_annex_b_stream() {
+    let slides = [(64, 48, "png"), (64, 48, "jpg")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let output = dir.join("out.h264");
+    convert(&zip, &frames, &output, &ConvertOptions::new()).unwrap();
+    let data = std::fs::read(&output).unwrap();
+    assert!(data.starts_with(&[0, 0, 0, 1]));
+    let mut probe = Probe::new(&output);
+    assert_eq!(probe.frames().len(), 2);
+}
+
+#[test]
+fn writes_the_sample_aspect_ratio() {
+    let slides = [(64, 48, "png"), (64, 48, "jpg")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let options = ConvertOptions::new().sample_aspect_ratio((4, 3));
+    convert(&zip, &frames, &output, &options).unwrap();
+    let input = ffmpeg::format::input(&output).unwrap();
+    let stream = input.streams().best(ffmpeg::media::Type::Video).unwrap();
+    let sar = Rational::from(unsafe { (*stream.as_ptr()).sample_aspect_ratio });
+    assert_eq!(sar, Rational(4, 3));
+    let mut probe = Probe::new(&output);
+    assert_eq!(probe.decoder.aspect_ratio(), Rational(4, 3));
+    // The bitstream carries it too, for players that ignore the container.
+    assert!(probe
+        .frames()
+        .iter()
+        .all(|frame| frame.aspect_ratio() == Rational(4, 3)));
+}
+
+#[test]
+fn marks_every_slide_as_a_chapter() {
+    let slides = [(64, 48, "png"), (64, 48, "jpg"), (64, 48, "png")];
+    let zip = make_test_zip(&slides);
+    let mut frames = test_frames(&slides, 400);
+    frames[1] = frames[1].clone().with_chapter_title("Results");
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let options = ConvertOptions::new().chapters(true);
+    convert(&zip, &frames, &output, &options).unwrap();
+    let input = ffmpeg::format::input(&output).unwrap();
+    assert_eq!(input.chapters().count(), frames.len());
+    let chapters: Vec<_> = input
+        .chapters()
+        .map(|chapter| {
+            let to_ms = |ts: i64| ts.rescale(chapter.time_base(), Rational(1, 1_000));
+            let title = chapter.metadata().get("title").map(str::to_owned);
+            (to_ms(chapter.start()), to_ms(chapter.end()), title)
+        })
+        .collect();
+    let title = |title: &str| Some(title.to_owned());
+    assert_eq!(
+        chapters,
+        [
+            (0, 400, title("Slide 1")),
+            (400, 800, title("Results")),
+            (800, 1_200, title("Slide 3")),
+        ]
+    );
+}
+
+#[test]
+fn tags_the_stream_with_its_colors() {
+    use ffmpeg::color;
+    let slides = [(64, 48, "png")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let output = dir.join("rgb.mp4");
+    convert(&zip, &frames, &output, &ConvertOptions::new()).unwrap();
+    let probe = Probe::new(&output);
+    assert_eq!(probe.decoder.color_space(), color::Space::BT709);
+    assert_eq!(probe.decoder.color_range(), color::Range::JPEG);
+    let output = dir.join("bt601.mp4");
+    let options = ConvertOptions::new()
+        .colorspace(ColorSpace::Bt601)
+        .color_range(ColorRange::Tv)
+        .color_primaries(ColorPrimaries::Smpte170m);
+    convert(&zip, &frames, &output, &options).unwrap();
+    let probe = Probe::new(&output);
+    assert_eq!(probe.decoder.color_space(), color::Space::SMPTE170M);
+    assert_eq!(probe.decoder.color_range(), color::Range::MPEG);
+    assert_eq!(probe.decoder.color_primaries(), color::Primaries::SMPTE170M);
+}
+
+#[test]
+fn rotating_a_quarter_turn_swaps_the_dimensions() {
+    let slides = [(320, 240, "png"), (320, 240, "jpg")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let size = |transform| {
+        let output = dir.join("out.mp4");
+        let options = ConvertOptions::new().transform(transform);
+        convert(&zip, &frames, &output, &options).unwrap();
+        let probe = Probe::new(&output);
+        (probe.decoder.width(), probe.decoder.height())
+    };
+    assert_eq!(size(Transform::Rotate90), (240, 320));
+    assert_eq!(size(Transform::Rotate270), (240, 320));
+    assert_eq!(size(Transform::Rotate180), (320, 240));
+    assert_eq!(size(Transform::FlipH), (320, 240));
+}
+
+#[test]
+fn turns_sideways_photos_upright() {
+    let zip = make_zip(&[
+        ("0.jpg", test_util::make_oriented_jpeg(320, 240, 6)),
+        ("1.jpg", test_util::make_image(320, 240, "jpg")),
+    ]);
+    let dir = TempDir::new();
+    let size = |filename, options: &ConvertOptions| {
+        let output = dir.join("out.mp4");
+        let frames = [Frame::new(filename, 100)];
+        convert(&zip, &frames, &output, options).unwrap();
+        let probe = Probe::new(&output);
+        (probe.decoder.width(), probe.decoder.height())
+    };
+    let options = ConvertOptions::new();
+    assert_eq!(size("0.jpg", &options), (240, 320));
+    assert_eq!(size("1.jpg", &options), (320, 240));
+    assert_eq!(size("0.jpg", &options.auto_orient(false)), (320, 240));
+}
+
+#[test]
+fn crops_to_the_clamped_region() {
+    let slides = [(200, 200, "png")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 100));
+    let dir = TempDir::new();
+    let crop = |rect| {
+        let output = dir.join("out.mp4");
+        let options = ConvertOptions::new().crop(rect);
+        convert(&zip, &frames, &output, &options).map(|_| {
+            let probe = Probe::new(&output);
+            (probe.decoder.width(), probe.decoder.height())
+        })
+    };
+    assert_eq!(crop(Rect::new(50, 50, 100, 100)).unwrap(), (100, 100));
+    assert_eq!(crop(Rect::new(150, 120, 100, 100)).unwrap(), (50, 80));
+    let err = crop(Rect::new(200, 0, 10, 10)).unwrap_err();
+    assert!(err.to_string().contains("outside the 200x200 frame"));
+}
+
+#[test]
+fn joins_archives_into_one_video() {
+    let first = [(64, 48, "png"), (64, 48, "png")];
+    let second = [(64, 48, "jpg"), (64, 48, "jpg")];
+    let (first_zip, first_frames) = (make_test_zip(&first), test_frames(&first, 100));
+    let (second_zip, second_frames) = (make_test_zip(&second), test_frames(&second, 200));
+    let sources = [
+        (first_zip, first_frames.as_slice()),
+        (second_zip, second_frames.as_slice()),
+    ];
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let stats = convert_many(&sources, &output, &ConvertOptions::new()).unwrap();
+    assert_eq!(stats.slide_sizes.len(), 4);
+    let mut probe = Probe::new(&output);
+    assert_eq!(probe.frames().len(), 4);
+    assert!((probe.duration_ms - 600).abs() <= 10);
+    // Timestamps carry on across the archives rather than starting over.
+    let mut pts: Vec<_> = probe.packets.iter().filter_map(Packet::pts).collect();
+    pts.sort_unstable();
+    pts.dedup();
+    assert_eq!(pts.len(), 4);
+}
+
+#[test]
+fn holds_the_last_slide() {
+    let slides = [(64, 48, "png"), (64, 48, "png")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 500));
+    let options = ConvertOptions::new().end_hold_ms(1_000);
+    let estimate = estimate_with(&frames, &options).unwrap();
+    assert_eq!(estimate.total_duration, Duration::from_millis(2_000));
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let stats = convert(&zip, &frames, &output, &options).unwrap();
+    assert_eq!(stats.timing_mode, TimingMode::Variable);
+    let probe = Probe::new(&output);
+    assert!((probe.duration_ms - 2_000).abs() <= 10);
+}
+
+#[test]
+fn holds_a_single_slide() {
+    let slides = [(64, 48, "png")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 1_000));
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let options = ConvertOptions::new().end_hold_ms(500);
+    convert(&zip, &frames, &output, &options).unwrap();
+    let probe = Probe::new(&output);
+    // The slide and its repeat, in that order.
+    let pts: Vec<_> = probe.packets.iter().filter_map(Packet::pts).collect();
+    assert_eq!(pts.len(), 2);
+    assert!(pts[0] < pts[1]);
+    assert!((probe.duration_ms - 1_500).abs() <= 10);
+}
+
+#[test]
+fn converts_an_encrypted_archive() {
+    let slides = [(64, 48, "png"), (64, 48, "jpg")];
+    let entries: Vec<_> = slides
+        .iter()
+        .enumerate()
+        .map(|(index, &(width, height, format))| {
+            let name = format!("{}.{}", index, format);
+            (name, test_util::make_image(width, height, format))
+        })
+        .collect();
+    let zip = test_util::make_encrypted_zip(&entries, b"secret");
+    let frames = test_frames(&slides, 100);
+    let dir = TempDir::new();
+    let err = convert(
+        &zip,
+        &frames,
+        dir.join("locked.mp4"),
+        &ConvertOptions::new(),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(SlidevidError::EncryptedArchive(_))
+    ));
+    let output = dir.join("out.mp4");
+    let options = ConvertOptions::new().password("secret");
+    convert(&zip, &frames, &output, &options).unwrap();
+    assert_eq!(Probe::new(&output).frames().len(), 2);
+}
+
+#[test]
+fn fits_assorted_slides_into_a_fixed_canvas() {
+    let slides = [(320, 240, "png"), (200, 400, "jpg"), (1920, 1080, "png")];
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let options = ConvertOptions::new().output_size(1280, 720);
+    convert(
+        make_test_zip(&slides),
+        &test_frames(&slides, 100),
+        &output,
+        &options,
+    )
+    .unwrap();
+    let mut probe = Probe::new(&output);
+    assert_eq!((probe.decoder.width(), probe.decoder.height()), (1280, 720));
+    assert_eq!(probe.frames().len(), 3);
+    let options = ConvertOptions::new().output_size(1279, 720);
+    let err = convert(
+        make_test_zip(&slides),
+        &test_frames(&slides, 100),
+        dir.join("odd.mp4"),
+        &options,
+    );
+    assert!(err.is_err());
+}
+
+#[test]
+fn converts_a_batch_in_order() {
+    let slides = [(64, 48, "png"), (64, 48, "jpg")];
+    let dir = TempDir::new();
+    let jobs: Vec<_> = (0..5)
+        .map(|index| {
+            let mut frames = test_frames(&slides, 100 * (index + 1));
+            if index == 2 {
+                frames.push(Frame::new("missing.png".to_owned(), 100));
+            }
+            let output = dir.join(&format!("{}.mp4", index));
+            Job::new(make_test_zip(&slides), frames, output)
+        })
+        .collect();
+    let results = convert_batch(jobs, 3);
+    assert_eq!(results.len(), 5);
+    assert!(results[2].is_err());
+    for (index, result) in results.iter().enumerate() {
+        match result {
+            Err(err) => {
+                assert_eq!(index, 2);
+                assert!(matches!(
+                    err.downcast_ref(),
+                    Some(SlidevidError::MissingFrame { .. })
+                ));
+            }
+            Ok(stats) => {
+                assert_eq!(stats.output_path, dir.join(&format!("{}.mp4", index)));
+                let expected = 200 * (index as i64 + 1);
+                assert!((Probe::new(&stats.output_path).duration_ms - expected).abs() <= 10);
+            }
+        }
+    }
+    assert!(convert_batch(Vec::new(), 4).is_empty());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn frames_and_options_round_trip_through_json() {
+    let frames = vec![
+        Frame::new("0.png".to_owned(), 500),
+        Frame::without_delay("1.png".to_owned()).with_chapter_title("Intro"),
+    ];
+    let json = serde_json::to_string(&frames).unwrap();
+    assert_eq!(
+        json,
+        r#"[{"filename":"0.png","delay":500},{"filename":"1.png","chapter_title":"Intro"}]"#
+    );
+    let parsed: Vec<Frame<String>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, frames);
+    let options = ConvertOptions::new()
+        .min_slide_ms(100)
+        .rate_control(RateControl::Cbr(500_000))
+        .container(Container::Mkv);
+    let json = serde_json::to_string(&options).unwrap();
+    let parsed: ConvertOptions = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.min_slide_ms, Some(100));
+    assert_eq!(parsed.rate_control, RateControl::Cbr(500_000));
+    assert_eq!(parsed.container, Container::Mkv);
+}
+
+#[cfg(feature = "incremental")]
+#[test]
+fn reencodes_when_a_subtitle_changes() {
+    let slides = [(64, 48, "png"), (64, 48, "png")];
+    let (zip, frames) = (make_test_zip(&slides), test_frames(&slides, 500));
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    let mut incremental = IncrementalConvert::new(&dir.join("manifest.json"))
+        .unwrap()
+        .options(ConvertOptions::new().write_mode(WriteMode::Overwrite));
+    assert!(incremental.run(&zip, &frames, &output).unwrap().is_some());
+    assert!(incremental.run(&zip, &frames, &output).unwrap().is_none());
+    let mut subtitled = frames.clone();
+    subtitled[1] = subtitled[1].clone().with_subtitle("Questions?");
+    assert!(incremental
+        .run(&zip, &subtitled, &output)
+        .unwrap()
+        .is_some());
+    assert!(incremental
+        .run(&zip, &subtitled, &output)
+        .unwrap()
+        .is_none());
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn encodes_16_bit_images_as_they_are() {
+    use image::{DynamicImage, ImageBuffer, Luma, Rgba};
+    // Read with the wrong byte order, the bright slide would come out dark and the dark one bright.
+    let gray = |value| DynamicImage::ImageLuma16(ImageBuffer::from_pixel(64, 48, Luma([value])));
+    let float = DynamicImage::ImageRgba32F(ImageBuffer::from_pixel(64, 48, Rgba([1.0; 4])));
+    let images = vec![(gray(0xff00), 100), (gray(0x00ff), 100), (float, 100)];
+    let (mut source, frames) = crate::ImageSource::new(images);
+    let dir = TempDir::new();
+    let output = dir.join("out.mp4");
+    convert_from(&mut source, &frames, &output, &ConvertOptions::default()).unwrap();
+    let luma: Vec<_> = Probe::new(&output)
+        .frames()
+        .iter()
+        .map(|frame| frame.data(0)[frame.stride(0) * 24 + 32])
+        .collect();
+    assert_eq!(luma.len(), 3);
+    assert!(luma[0] > 200 && luma[1] < 50 && luma[2] > 200, "{:?}", luma);
+}
+
+#[test]
+fn flattens_transparent_palette_colors() {
+    ffmpeg::init().unwrap();
+    let paletted = |argb: u32| {
+        let mut frame = ffmpeg::frame::Video::new(format::Pixel::PAL8, 4, 4);
+        frame.data_mut(0).fill(0);
+        let palette = unsafe { (*frame.as_mut_ptr()).data[1] as *mut u32 };
+        unsafe { std::slice::from_raw_parts_mut(palette, 256) }.fill(argb);
+        frame
+    };
+    let compositor = &mut alpha::Compositor::new(Rgb([0, 0, 0xff]));
+    let opaque = paletted(0xffff_0000);
+    assert_eq!(
+        compositor.apply(&opaque).unwrap().format(),
+        format::Pixel::PAL8
+    );
+    let transparent = paletted(0x00ff_0000);
+    let flattened = compositor.apply(&transparent).unwrap();
+    assert_eq!(flattened.format(), format::Pixel::RGB24);
+    assert_eq!(&flattened.data(0)[..3], &[0, 0, 0xff]);
+}
@@ -0,0 +1,429 @@
+use crate::{ConvertOptions, Frame, MILLIS};
+use anyhow::{anyhow, Result};
+use ffmpeg::Rational;
+use std::{convert::TryFrom, time::Duration};
+
+// Shortest slide duration, which is also the finest the encoder time base gets.
+pub(crate) const MIN_DELAY_MS: u32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EstimatedOutput {
+    pub total_duration: Duration,
+    pub frame_count: usize,
+    pub fps: f64,
+}
+
+/// Whether every frame of the output lasts the same time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimingMode {
+    /// Constant frame rate, in frames per second.
+    Constant(Rational),
+    Variable,
+}
+
+/// The timing mode of an output with slides lasting `delays`, as `resolve_delays` returns them.
+pub(crate) fn timing_mode(delays: &[u32], options: &ConvertOptions) -> TimingMode {
+    // Holding the last slide repeats it one tick before the end, which no frame rate fits.
+    let held = options.end_hold_ms.is_some_and(|hold| hold > 0);
+    match delays.split_first() {
+        Some((&first, rest)) if !held && rest.iter().all(|&delay| delay == first) => {
+            TimingMode::Constant(Rational(MILLIS, first as i32).reduce())
+        }
+        _ => TimingMode::Variable,
+    }
+}
+
+pub(crate) fn select_frames<'a, S: AsRef<str>>(
+    frames: &'a [Frame<S>],
+    options: &ConvertOptions,
+) -> Result<&'a [Frame<S>]> {
+    let range = match &options.frame_range {
+        Some(range) => range,
+        None => return Ok(frames),
+    };
+    if range.start >= range.end || range.end > frames.len() {
+        return Err(anyhow!(
+            "Frame range {}..{} is out of bounds for {} frames",
+            range.start,
+            range.end,
+            frames.len()
+        ));
+    }
+    Ok(&frames[range.clone()])
+}
+
+pub(crate) fn resolve_delays<S: AsRef<str>>(
+    frames: &[Frame<S>],
+    options: &ConvertOptions,
+) -> Result<Vec<u32>> {
+    let min = options.min_slide_ms.unwrap_or(0);
+    let max = options.max_slide_ms.unwrap_or(u32::MAX);
+    if min > max {
+        return Err(anyhow!(
+            "Minimum slide duration {}ms exceeds the maximum of {}ms",
+            min,
+            max
+        ));
+    }
+    if max < MIN_DELAY_MS {
+        return Err(anyhow!(
+            "Maximum slide duration {}ms is below the minimum supported {}ms",
+            max,
+            MIN_DELAY_MS
+        ));
+    }
+    let speed = options.speed_factor;
+    if !(speed > 0.0 && speed.is_finite()) {
+        return Err(anyhow!("Speed factor must be positive, got {}", speed));
+    }
+    let mut delays = frames
+        .iter()
+        .map(|f| {
+            let delay = f.delay_or(options.default_delay_ms)?;
+            let delay = (delay as f64 / speed).round() as u32;
+            Ok(delay.max(min).min(max))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let floor = match options.max_fps {
+        Some(0) => return Err(anyhow!("Maximum frame rate must be positive")),
+        Some(max_fps) => (MILLIS as u32).div_ceil(max_fps).max(MIN_DELAY_MS),
+        None => MIN_DELAY_MS,
+    };
+    if floor > max {
+        return Err(anyhow!(
+            "A frame at {}fps lasts {}ms, longer than the maximum slide duration of {}ms",
+            options.max_fps.unwrap_or_default(),
+            floor,
+            max
+        ));
+    }
+    cap_frame_rate(&mut delays, floor);
+    if let (Some(last), Some(hold)) = (delays.last_mut(), options.end_hold_ms) {
+        *last = last.saturating_add(hold);
+    }
+    for &delay in &delays {
+        check_decoder_time_base(options.decoder_time_base, delay)?;
+    }
+    Ok(delays)
+}
+
+/// Fails unless `delay` milliseconds are a whole number of `time_base` ticks.
+pub(crate) fn check_decoder_time_base(time_base: Rational, delay: u32) -> Result<()> {
+    let (num, den) = (time_base.numerator(), time_base.denominator());
+    if num <= 0 || den <= 0 {
+        return Err(anyhow!(
+            "Decoder time base must be positive, got {}",
+            time_base
+        ));
+    }
+    if delay as i64 * den as i64 % (num as i64 * MILLIS as i64) != 0 {
+        return Err(anyhow!(
+            "Decoder time base {} can't represent a slide duration of {}ms",
+            time_base,
+            delay
+        ));
+    }
+    Ok(())
+}
+
+// Each slide ends where it originally did or `floor` milliseconds after the previous one,
+// whichever is later, so a run of short slides pushes into the slides after it instead of
+// stretching the video.
+fn cap_frame_rate(delays: &mut [u32], floor: u32) {
+    let (mut original_end, mut end) = (0u64, 0u64);
+    for delay in delays {
+        original_end += *delay as u64;
+        let next = original_end.max(end + floor as u64);
+        *delay = (next - end) as u32;
+        end = next;
+    }
+}
+
+/// Start and end of each slide in milliseconds, matching the timestamps slides are encoded with.
+pub(crate) fn slide_spans(delays: &[u32]) -> impl Iterator<Item = (i64, i64)> + '_ {
+    delays.iter().scan(0, |start, &delay| {
+        let span = (*start, *start + delay as i64);
+        *start = span.1;
+        Some(span)
+    })
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+pub(crate) fn gcd_delays(delays: &[u32]) -> Option<u32> {
+    delays.iter().copied().reduce(gcd)
+}
+
+// Using the GCD rather than the shortest delay keeps every delay an exact number of ticks where
+// it's at least the floor. Below that, slides are timestamped to the nearest tick instead, which
+// keeps coprime delays from exploding the tick count; every slide lasts at least one tick, so
+// none of them share a timestamp. The end hold counts as a delay of its own, so the repeat of
+// the last slide one tick before the end comes after the slide itself.
+pub(crate) fn encoder_time_base(delays: &[u32], options: &ConvertOptions) -> Result<Rational> {
+    let tick = gcd_delays(delays).ok_or_else(|| anyhow!("Slide show with 0 frames?!"))?;
+    let tick = match options.end_hold_ms {
+        Some(hold) if hold > 0 => gcd(tick, hold),
+        _ => tick,
+    };
+    let tick = tick.max(MIN_DELAY_MS) as i32;
+    Ok(Rational(tick, MILLIS))
+}
+
+/// Frames shown from each of `starts` until the next, and the last one until `end`, for decks
+/// timed by presentation time rather than delay. Times are rounded to the millisecond, each
+/// frame lasting the difference between its rounded start and end so none of them drift.
+pub fn frames_from_timestamps<S: AsRef<str>>(
+    filenames: Vec<S>,
+    starts: &[Duration],
+    end: Duration,
+) -> Result<Vec<Frame<S>>> {
+    if filenames.len() != starts.len() {
+        return Err(anyhow!(
+            "Got {} timestamps for {} frames",
+            starts.len(),
+            filenames.len()
+        ));
+    }
+    let millis: Vec<u128> = starts
+        .iter()
+        .chain(Some(&end))
+        .map(|time| (time.as_micros() + 500) / 1_000)
+        .collect();
+    filenames
+        .into_iter()
+        .zip(millis.windows(2))
+        .enumerate()
+        .map(|(index, (filename, pair))| {
+            if pair[1] <= pair[0] {
+                return Err(anyhow!(
+                    "Frame {} starts at {}ms, not before the {}ms after it",
+                    index,
+                    pair[0],
+                    pair[1]
+                ));
+            }
+            let delay = u32::try_from(pair[1] - pair[0])
+                .map_err(|_| anyhow!("Frame {} lasts longer than {}ms", index, u32::MAX))?;
+            Ok(Frame::new(filename, delay))
+        })
+        .collect()
+}
+
+pub fn estimate<S: AsRef<str>>(frames: &[Frame<S>]) -> Result<EstimatedOutput> {
+    estimate_with(frames, &ConvertOptions::default())
+}
+
+pub fn estimate_with<S: AsRef<str>>(
+    frames: &[Frame<S>],
+    options: &ConvertOptions,
+) -> Result<EstimatedOutput> {
+    let frames = select_frames(frames, options)?;
+    let delays = resolve_delays(frames, options)?;
+    let time_base = encoder_time_base(&delays, options)?;
+    let total: u64 = delays.iter().map(|&d| d as u64).sum();
+    Ok(EstimatedOutput {
+        total_duration: Duration::from_millis(total),
+        frame_count: delays.len(),
+        fps: time_base.invert().into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_the_default_delay() {
+        let frames = [
+            Frame::new("0.png", 250),
+            Frame::without_delay("1.png"),
+            Frame::new("2.png", 40),
+        ];
+        let options = ConvertOptions::new().default_delay_ms(1_000);
+        assert_eq!(
+            resolve_delays(&frames, &options).unwrap(),
+            vec![250, 1_000, 40]
+        );
+        assert!(resolve_delays(&frames, &ConvertOptions::new()).is_err());
+    }
+
+    #[test]
+    fn floors_short_slides_without_stretching_the_video() {
+        let frames = [
+            Frame::new("0.png", 3),
+            Frame::new("1.png", 20),
+            Frame::new("2.png", 20),
+        ];
+        let options = ConvertOptions::new();
+        let delays = resolve_delays(&frames, &options).unwrap();
+        assert_eq!(delays, vec![10, 13, 20]);
+        assert_eq!(
+            encoder_time_base(&delays, &options).unwrap(),
+            Rational(10, MILLIS)
+        );
+        assert_eq!(
+            encoder_time_base(&[40, 60], &options).unwrap(),
+            Rational(20, MILLIS)
+        );
+    }
+
+    #[test]
+    fn clamps_slide_durations() {
+        let frames = [
+            Frame::new("0.png", 5),
+            Frame::new("1.png", 120_000),
+            Frame::new("2.png", 700),
+        ];
+        let options = ConvertOptions::new().min_slide_ms(50).max_slide_ms(10_000);
+        let delays = resolve_delays(&frames, &options).unwrap();
+        assert_eq!(delays, vec![50, 10_000, 700]);
+        assert_eq!(
+            encoder_time_base(&delays, &options).unwrap(),
+            Rational(50, MILLIS)
+        );
+        let options = ConvertOptions::new().min_slide_ms(500).max_slide_ms(100);
+        assert!(resolve_delays(&frames, &options).is_err());
+        let options = ConvertOptions::new().max_slide_ms(5);
+        assert!(resolve_delays(&frames, &options).is_err());
+    }
+
+    #[test]
+    fn gives_the_end_hold_its_own_tick() {
+        let frames = [Frame::new("0.png", 1_000)];
+        let options = ConvertOptions::new().end_hold_ms(500);
+        let delays = resolve_delays(&frames, &options).unwrap();
+        assert_eq!(delays, vec![1_500]);
+        assert_eq!(
+            encoder_time_base(&delays, &options).unwrap(),
+            Rational(500, MILLIS)
+        );
+        let options = ConvertOptions::new().end_hold_ms(0);
+        let delays = resolve_delays(&frames, &options).unwrap();
+        assert_eq!(
+            encoder_time_base(&delays, &options).unwrap(),
+            Rational(1_000, MILLIS)
+        );
+    }
+
+    #[test]
+    fn times_frames_by_their_timestamps() {
+        let starts = [
+            Duration::ZERO,
+            Duration::from_millis(1_500),
+            Duration::from_micros(2_250_400),
+        ];
+        let end = Duration::from_secs(4);
+        let frames = frames_from_timestamps(vec!["0.png", "1.png", "2.png"], &starts, end).unwrap();
+        let delays: Vec<_> = frames.iter().map(|frame| frame.delay).collect();
+        assert_eq!(delays, [Some(1_500), Some(750), Some(1_750)]);
+        let unordered = [Duration::from_secs(1), Duration::from_secs(1)];
+        assert!(frames_from_timestamps(vec!["0.png", "1.png"], &unordered, end).is_err());
+        assert!(frames_from_timestamps(vec!["0.png"], &starts, end).is_err());
+    }
+
+    #[test]
+    fn detects_constant_frame_rates() {
+        let options = ConvertOptions::new();
+        assert_eq!(
+            timing_mode(&[40, 40, 40], &options),
+            TimingMode::Constant(Rational(25, 1))
+        );
+        assert_eq!(
+            timing_mode(&[1_500, 1_500], &options),
+            TimingMode::Constant(Rational(2, 3))
+        );
+        assert_eq!(timing_mode(&[40, 60], &options), TimingMode::Variable);
+        let held = ConvertOptions::new().end_hold_ms(500);
+        assert_eq!(timing_mode(&[40, 40], &held), TimingMode::Variable);
+    }
+
+    #[test]
+    fn estimates_from_the_delays() {
+        let frames = [
+            Frame::new("0.png", 500),
+            Frame::new("1.png", 1_500),
+            Frame::new("2.png", 1_000),
+        ];
+        let expected = EstimatedOutput {
+            total_duration: Duration::from_millis(3_000),
+            frame_count: 3,
+            fps: 2.0,
+        };
+        assert_eq!(estimate(&frames).unwrap(), expected);
+        let options = ConvertOptions::new().frame_range(1, 3).speed_factor(2.0);
+        let expected = EstimatedOutput {
+            total_duration: Duration::from_millis(1_250),
+            frame_count: 2,
+            fps: 4.0,
+        };
+        assert_eq!(estimate_with(&frames, &options).unwrap(), expected);
+        let steady = [Frame::new("0.png", 40), Frame::new("1.png", 40)];
+        assert_eq!(estimate(&steady).unwrap().fps, 25.0);
+        assert!(estimate::<&str>(&[]).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "test-proptest"))]
+mod proptests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn total(delays: &[u32]) -> u64 {
+        delays.iter().map(|&delay| delay as u64).sum()
+    }
+
+    proptest! {
+        #[test]
+        fn timestamps_keep_the_duration(
+            start in 0u64..10_000_000,
+            gaps in prop::collection::vec(1_000u64..5_000_000, 1..30),
+        ) {
+            let starts: Vec<_> = gaps
+                .iter()
+                .scan(start, |time, gap| {
+                    let start = *time;
+                    *time += gap;
+                    Some(Duration::from_micros(start))
+                })
+                .collect();
+            let end = Duration::from_micros(start + gaps.iter().sum::<u64>());
+            let filenames = vec!["slide.png"; gaps.len()];
+            let frames = frames_from_timestamps(filenames, &starts, end).unwrap();
+            let delays: Vec<_> = frames.iter().map(|frame| frame.delay.unwrap()).collect();
+            let millis = |time: Duration| (time.as_micros() as u64 + 500) / 1_000;
+            prop_assert_eq!(total(&delays), millis(end) - millis(starts[0]));
+        }
+
+        #[test]
+        fn resolving_keeps_long_enough_slides(
+            delays in prop::collection::vec(MIN_DELAY_MS..100_000, 1..30),
+        ) {
+            let frames: Vec<_> = delays
+                .iter()
+                .map(|&delay| Frame::new("slide.png", delay))
+                .collect();
+            prop_assert_eq!(resolve_delays(&frames, &ConvertOptions::new()).unwrap(), delays);
+        }
+
+        #[test]
+        fn capping_leaves_no_delay_below_the_floor(
+            original in prop::collection::vec(1u32..200, 1..30),
+            floor in MIN_DELAY_MS..100,
+        ) {
+            let mut delays = original.clone();
+            cap_frame_rate(&mut delays, floor);
+            prop_assert!(delays.iter().all(|&delay| delay >= floor));
+            // No slide ends earlier than it did, and the video only runs longer when its last
+            // slide had to be pushed back.
+            let mut ends = slide_spans(&delays).zip(slide_spans(&original));
+            prop_assert!(ends.all(|((_, end), (_, original))| end >= original));
+            prop_assert!(total(&delays) == total(&original) || delays.last() == Some(&floor));
+        }
+    }
+}
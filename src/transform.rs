@@ -0,0 +1,269 @@
+use crate::{
+    alpha::Compositor, crop, subtitle::Subtitler, ConvertOptions, PendingSlide, Rect, Rgb,
+};
+use anyhow::{anyhow, Result};
+use ffmpeg::{filter, format::Pixel, frame};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Transform {
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipH,
+    FlipV,
+}
+
+impl Transform {
+    fn filter_spec(self) -> &'static str {
+        match self {
+            Transform::Rotate90 => "transpose=clock",
+            Transform::Rotate180 => "hflip,vflip",
+            Transform::Rotate270 => "transpose=cclock",
+            Transform::FlipH => "hflip",
+            Transform::FlipV => "vflip",
+        }
+    }
+
+    pub fn swaps_dimensions(self) -> bool {
+        matches!(self, Transform::Rotate90 | Transform::Rotate270)
+    }
+}
+
+/// What fills the bars around slides fitted into a different aspect ratio.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LetterboxFill {
+    #[default]
+    Black,
+    Color(Rgb),
+    /// A blurred copy of the slide scaled to cover the whole frame.
+    Blur,
+}
+
+impl LetterboxFill {
+    /// The filters scaling a slide with `fit` and centering it over this fill in `width` by
+    /// `height`.
+    fn filter_spec(self, fit: &str, width: u32, height: u32) -> String {
+        let pad = |color: &str| {
+            format!(
+                "{},pad={}:{}:(ow-iw)/2:(oh-ih)/2:color={}",
+                fit, width, height, color
+            )
+        };
+        match self {
+            LetterboxFill::Black => pad("black"),
+            LetterboxFill::Color(Rgb([r, g, b])) => {
+                pad(&format!("0x{:02x}{:02x}{:02x}", r, g, b))
+            }
+            LetterboxFill::Blur => format!(
+                "split[bg][fg];\
+                 [bg]scale={w}:{h}:force_original_aspect_ratio=increase,crop={w}:{h},boxblur=20[blurred];\
+                 [fg]{fit}[fitted];[blurred][fitted]overlay=(W-w)/2:(H-h)/2",
+                w = width,
+                h = height,
+                fit = fit
+            ),
+        }
+    }
+}
+
+/// The filters that turn `decoded` upright according to the EXIF orientation the JPEG decoder
+/// exports, if it isn't already.
+fn orientation_spec(decoded: &frame::Video) -> Option<&'static str> {
+    match decoded.metadata().get("Orientation")?.trim() {
+        "2" => Some("hflip"),
+        "3" => Some("hflip,vflip"),
+        "4" => Some("vflip"),
+        "5" => Some("transpose=cclock_flip"),
+        "6" => Some("transpose=clock"),
+        "7" => Some("transpose=clock_flip"),
+        "8" => Some("transpose=cclock"),
+        _ => None,
+    }
+}
+
+pub(crate) struct Transformer {
+    crop: Option<Rect>,
+    transform: Option<Transform>,
+    auto_orient: bool,
+    letterbox_fill: LetterboxFill,
+    /// Size of the first slide coming out of the transform, or the fixed output size, which
+    /// slides with their own transform are fitted into.
+    size: Option<(u32, u32)>,
+    /// Whether every slide is fitted into `size`, which is then the fixed output size.
+    fit_all: bool,
+    graph: Option<(Graph, String)>,
+    compositor: Compositor,
+    subtitler: Subtitler,
+    burn_subtitles: bool,
+}
+
+impl Transformer {
+    pub(crate) fn new(options: &ConvertOptions) -> Self {
+        Self {
+            crop: options.crop,
+            transform: options.transform,
+            auto_orient: options.auto_orient,
+            letterbox_fill: options.letterbox_fill,
+            size: options.output_size,
+            fit_all: options.output_size.is_some(),
+            graph: None,
+            compositor: Compositor::new(options.background_color),
+            subtitler: Subtitler::new(options.subtitle_style.clone()),
+            burn_subtitles: options.burn_subtitles,
+        }
+    }
+
+    /// Crops `decoded` in place, turns it upright, applies the slide's own transform or else the
+    /// global one, scales it to the slide's own size, flattens any alpha channel and burns in
+    /// its subtitle if subtitles are burned in, returning `decoded` itself if nothing else had to
+    /// change.
+    pub(crate) fn apply<'a>(
+        &'a mut self,
+        decoded: &'a mut frame::Video,
+        slide: &PendingSlide,
+    ) -> Result<&'a frame::Video> {
+        let transform = slide.transform;
+        if let Some(rect) = self.crop {
+            crop::apply(decoded, rect)?;
+        }
+        let orientation = self
+            .auto_orient
+            .then(|| orientation_spec(decoded))
+            .flatten();
+        let mut spec = match (orientation, transform.or(self.transform)) {
+            (Some(orientation), Some(transform)) => {
+                Some(format!("{},{}", orientation, transform.filter_spec()))
+            }
+            (orientation, transform) => orientation
+                .or_else(|| transform.map(Transform::filter_spec))
+                .map(str::to_owned),
+        };
+        let fit = transform.is_some() || self.fit_all;
+        let scale = match (slide.size, self.size) {
+            (Some((w, h)), Some((width, height))) if w > width || h > height => {
+                return Err(anyhow!(
+                    "Slide size {}x{} doesn't fit in the {}x{} output",
+                    w,
+                    h,
+                    width,
+                    height
+                ));
+            }
+            (Some((w, h)), size) => {
+                let scaling = slide.scaling.unwrap_or_default().filter_flags();
+                let resize = format!("scale={}:{}:flags={}", w, h, scaling);
+                // The first slide sets the output size, so there's nothing to center it in.
+                Some(match size {
+                    Some((width, height)) => {
+                        self.letterbox_fill.filter_spec(&resize, width, height)
+                    }
+                    None => resize,
+                })
+            }
+            (None, Some((width, height))) if fit => {
+                let resize = format!(
+                    "scale={}:{}:force_original_aspect_ratio=decrease",
+                    width, height
+                );
+                Some(self.letterbox_fill.filter_spec(&resize, width, height))
+            }
+            (None, _) => None,
+        };
+        if let Some(scale) = scale {
+            spec = Some(match spec {
+                Some(spec) => format!("{},{}", spec, scale),
+                None => scale,
+            });
+        }
+        let flattened = match spec {
+            Some(spec) => {
+                let input = (decoded.format(), decoded.width(), decoded.height());
+                let current = self
+                    .graph
+                    .as_ref()
+                    .map(|(graph, spec)| (graph.input(), spec.as_str()));
+                if current != Some((input, spec.as_str())) {
+                    self.graph = Some((Graph::new(&spec, input)?, spec));
+                }
+                let graph = &mut self.graph.as_mut().unwrap().0;
+                self.compositor.apply(graph.run(decoded)?)?
+            }
+            None => self.compositor.apply(decoded)?,
+        };
+        self.size
+            .get_or_insert((flattened.width(), flattened.height()));
+        let burn = self.burn_subtitles;
+        self.subtitler
+            .apply(flattened, slide.subtitle.filter(|_| burn))
+    }
+}
+
+/// A `buffer` to `buffersink` filter graph for frames of a single format and size.
+pub(crate) struct Graph {
+    graph: filter::Graph,
+    input: (Pixel, u32, u32),
+    output: frame::Video,
+}
+
+impl Graph {
+    pub(crate) fn new(spec: &str, input: (Pixel, u32, u32)) -> Result<Self> {
+        let (format, width, height) = input;
+        let format = format
+            .descriptor()
+            .ok_or_else(|| anyhow!("Unknown pixel format {:?}", format))?
+            .name();
+        let mut graph = filter::Graph::new();
+        graph.add(
+            &filter::find("buffer").ok_or_else(|| anyhow!("Couldn't find the buffer filter"))?,
+            "in",
+            &format!(
+                "video_size={}x{}:pix_fmt={}:time_base=1/1000:pixel_aspect=1/1",
+                width, height, format
+            ),
+        )?;
+        graph.add(
+            &filter::find("buffersink")
+                .ok_or_else(|| anyhow!("Couldn't find the buffersink filter"))?,
+            "out",
+            "",
+        )?;
+        graph.output("in", 0)?.input("out", 0)?.parse(spec)?;
+        graph.validate()?;
+        Ok(Self {
+            graph,
+            input,
+            output: frame::Video::empty(),
+        })
+    }
+
+    pub(crate) fn input(&self) -> (Pixel, u32, u32) {
+        self.input
+    }
+
+    pub(crate) fn run(&mut self, frame: &frame::Video) -> Result<&frame::Video> {
+        self.graph.get("in").unwrap().source().add(frame)?;
+        self.graph
+            .get("out")
+            .unwrap()
+            .sink()
+            .frame(&mut self.output)?;
+        Ok(&self.output)
+    }
+
+    /// Runs `frame` through on its own, flushing the graph for filters that only output once
+    /// they've seen the end of their input.
+    pub(crate) fn run_once(mut self, frame: &frame::Video) -> Result<frame::Video> {
+        let mut input = self.graph.get("in").unwrap();
+        let mut source = input.source();
+        source.add(frame)?;
+        source.flush()?;
+        self.graph
+            .get("out")
+            .unwrap()
+            .sink()
+            .frame(&mut self.output)?;
+        Ok(self.output)
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{decode_slide, ConvertOptions, Frame, FrameSource, SlidevidError, ZipSource};
+use anyhow::Result;
+use ffmpeg::format;
+use std::io::Cursor;
+
+#[derive(Clone, Debug)]
+pub struct SlideInfo {
+    pub index: usize,
+    pub filename: String,
+    pub width: u32,
+    pub height: u32,
+    pub format: format::Pixel,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    pub slides: Vec<SlideInfo>,
+    pub missing: Vec<String>,
+    pub undecodable: Vec<(String, String)>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.undecodable.is_empty()
+    }
+
+    /// The smallest and largest slide, by area.
+    pub fn resolution_spread(&self) -> Option<((u32, u32), (u32, u32))> {
+        let area = |slide: &&SlideInfo| slide.width as u64 * slide.height as u64;
+        let size = |slide: &SlideInfo| (slide.width, slide.height);
+        let min = self.slides.iter().min_by_key(area)?;
+        let max = self.slides.iter().max_by_key(area)?;
+        Some((size(min), size(max)))
+    }
+
+    /// Slides whose size differs from the first slide's.
+    pub fn mismatched_slides(&self) -> impl Iterator<Item = &SlideInfo> {
+        let first = self.slides.first().map(|slide| (slide.width, slide.height));
+        self.slides
+            .iter()
+            .filter(move |slide| Some((slide.width, slide.height)) != first)
+    }
+}
+
+pub fn validate<Z: AsRef<[u8]>, S: AsRef<str>>(
+    zip: Z,
+    frames: &[Frame<S>],
+) -> Result<ValidationReport> {
+    let options = &ConvertOptions::default();
+    validate_from(&mut ZipSource::new(Cursor::new(zip))?, frames, options)
+}
+
+/// Like [`validate`], reading the slides from `source` and decoding them as a conversion with
+/// `options` would.
+pub(crate) fn validate_from<F: FrameSource, S: AsRef<str>>(
+    source: &mut F,
+    frames: &[Frame<S>],
+    options: &ConvertOptions,
+) -> Result<ValidationReport> {
+    let mut report = ValidationReport::default();
+    for (index, frame) in frames.iter().enumerate() {
+        let filename = frame.filename.as_ref();
+        let result = decode_slide(source, filename, options);
+        if let Err(err) = &result {
+            if matches!(err.downcast_ref(), Some(SlidevidError::MissingFrame { .. })) {
+                report.missing.push(filename.to_owned());
+                continue;
+            }
+        }
+        match result {
+            Ok(decoded) => report.slides.push(SlideInfo {
+                index,
+                filename: filename.to_owned(),
+                width: decoded.width(),
+                height: decoded.height(),
+                format: decoded.format(),
+            }),
+            Err(err) => report
+                .undecodable
+                .push((filename.to_owned(), err.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{make_test_zip, make_zip, test_frames};
+
+    #[test]
+    fn reports_sizes_and_missing_slides() {
+        let slides = [(320, 240, "png"), (640, 480, "jpg")];
+        let mut frames = test_frames(&slides, 100);
+        frames.push(Frame::new("2.png".to_owned(), 100));
+        let report = validate(make_test_zip(&slides), &frames).unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.missing, vec!["2.png"]);
+        let sizes: Vec<_> = report.slides.iter().map(|s| (s.width, s.height)).collect();
+        assert_eq!(sizes, vec![(320, 240), (640, 480)]);
+        assert_eq!(report.resolution_spread(), Some(((320, 240), (640, 480))));
+        assert_eq!(report.mismatched_slides().count(), 1);
+    }
+
+    #[test]
+    fn reports_undecodable_slides() {
+        let zip = make_zip(&[("0.png", b"not a png".as_ref())]);
+        let report = validate(zip, &[Frame::new("0.png", 100)]).unwrap();
+        assert!(report.slides.is_empty());
+        assert_eq!(report.undecodable.len(), 1);
+        assert_eq!(report.undecodable[0].0, "0.png");
+    }
+}
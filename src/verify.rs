@@ -0,0 +1,84 @@
+use crate::{wrap_result, Frame};
+use anyhow::{anyhow, Result};
+use ffmpeg::{codec, frame, media};
+use std::path::Path;
+
+/// How far a frame's duration may stray from its slide's delay before it counts as a mismatch.
+const TOLERANCE_MS: f64 = 1.0;
+
+/// A decoded frame shown for longer or shorter than its slide's delay.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DelayMismatch {
+    pub index: usize,
+    pub expected_ms: u32,
+    pub actual_ms: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct VerifyReport {
+    pub expected_frames: usize,
+    pub decoded_frames: usize,
+    pub mismatches: Vec<DelayMismatch>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.expected_frames == self.decoded_frames && self.mismatches.is_empty()
+    }
+}
+
+/// Decodes every frame of the video at `path` and checks it against the `frames` it was
+/// converted from, as converted without any options that change the slides' timing. The last
+/// frame's duration is only checked if the container records the stream's duration.
+pub fn verify_output<S: AsRef<str>>(path: &Path, frames: &[Frame<S>]) -> Result<VerifyReport> {
+    let mut input = ffmpeg::format::input(&path)?;
+    let stream = input
+        .streams()
+        .best(media::Type::Video)
+        .ok_or_else(|| anyhow!("{:?} doesn't contain a video stream", path))?;
+    let (stream_index, time_base) = (stream.index(), stream.time_base());
+    let end = (stream.duration() > 0).then(|| stream.start_time().max(0) + stream.duration());
+    let mut decoder = codec::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .video()?;
+    let decoded = &mut frame::Video::empty();
+    let mut timestamps = Vec::new();
+    for (stream, packet) in input.packets() {
+        if stream.index() == stream_index {
+            decoder.send_packet(&packet)?;
+            while wrap_result(decoder.receive_frame(decoded))? {
+                timestamps.extend(decoded.timestamp().or_else(|| decoded.pts()));
+            }
+        }
+    }
+    decoder.send_eof()?;
+    while wrap_result(decoder.receive_frame(decoded))? {
+        timestamps.extend(decoded.timestamp().or_else(|| decoded.pts()));
+    }
+    let to_ms = |ts: i64| ts as f64 * f64::from(time_base) * 1_000.0;
+    let starts: Vec<f64> = timestamps.iter().map(|&ts| to_ms(ts)).collect();
+    let ends = starts
+        .iter()
+        .skip(1)
+        .copied()
+        .map(Some)
+        .chain(Some(end.map(to_ms)));
+    let mismatches = frames
+        .iter()
+        .zip(starts.iter().zip(ends))
+        .enumerate()
+        .filter_map(|(index, (frame, (start, end)))| {
+            let (expected_ms, actual_ms) = (frame.delay?, end? - start);
+            ((actual_ms - expected_ms as f64).abs() > TOLERANCE_MS).then_some(DelayMismatch {
+                index,
+                expected_ms,
+                actual_ms,
+            })
+        })
+        .collect();
+    Ok(VerifyReport {
+        expected_frames: frames.len(),
+        decoded_frames: timestamps.len(),
+        mismatches,
+    })
+}
@@ -0,0 +1,120 @@
+//! An in-memory entry point for running the conversion in the browser, on top of the C interface.
+//!
+//! FFmpeg itself isn't built by this crate, so `ffmpeg-sys-next` has to be pointed at a static
+//! FFmpeg cross-compiled for `wasm32-unknown-emscripten`, i.e. with `emconfigure`, and with
+//! `--disable-asm --disable-pthreads` as WebAssembly has neither. It needs at least the PNG and
+//! MJPEG decoders, libx264, swscale, the buffer filters and the mp4 muxer. Everything runs on the
+//! calling thread, so long decks should be converted from a web worker.
+//!
+//! The functions are plain C exports, as emscripten links C rather than `wasm-bindgen` glue, which
+//! only targets `wasm32-unknown-unknown`. Build a static library and link it together with FFmpeg:
+//!
+//! ```text
+//! cargo rustc --release --target wasm32-unknown-emscripten --features wasm --crate-type staticlib
+//! emcc target/wasm32-unknown-emscripten/release/libslidevid.a <FFmpeg and x264 libraries> \
+//!     -s EXPORTED_FUNCTIONS=_slidevid_convert_to_mp4,_slidevid_free,_slidevid_last_error_message,_malloc,_free \
+//!     -s EXPORTED_RUNTIME_METHODS=UTF8ToString,stringToNewUTF8 -s ALLOW_MEMORY_GROWTH=1 \
+//!     -o slidevid.js
+//! ```
+//!
+//! JavaScript then copies the zip and the `SlidevidFrame` array from `include/slidevid.h` into
+//! `_malloc`ed memory, passes two pointer-sized slots for the output, and reads the MP4 out of
+//! `HEAPU8` before handing it back to [`slidevid_free`].
+
+use crate::{
+    encode_to_stream,
+    ffi::{self, SlidevidFrame},
+};
+use anyhow::anyhow;
+use std::{io::Cursor, os::raw::c_int, ptr};
+
+/// Converts the `zip_len` bytes of a zip archive at `zip` into MP4 bytes, storing their address
+/// in `output` and their length in `output_len`, returning `SLIDEVID_OK` or one of the other
+/// `SLIDEVID_*` codes like `slidevid_convert`.
+///
+/// # Safety
+///
+/// `zip` must point to `zip_len` readable bytes and `frames` to `frame_count` frames, whose
+/// filenames are NUL-terminated UTF-8 strings, and `output` and `output_len` must be writable.
+/// The bytes are owned by the caller, who releases them with [`slidevid_free`].
+#[no_mangle]
+pub unsafe extern "C" fn slidevid_convert_to_mp4(
+    zip: *const u8,
+    zip_len: usize,
+    frames: *const SlidevidFrame,
+    frame_count: usize,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> c_int {
+    ffi::call(|| {
+        if output.is_null() || output_len.is_null() {
+            return Err(anyhow!("Null pointer argument"));
+        }
+        let (zip, frames) = ffi::read_input(zip, zip_len, frames, frame_count)?;
+        let mut mp4 = Cursor::new(Vec::new());
+        encode_to_stream(&mut mp4, "mp4", zip, &frames)?;
+        let mp4 = mp4.into_inner().into_boxed_slice();
+        *output_len = mp4.len();
+        *output = Box::into_raw(mp4) as *mut u8;
+        Ok(())
+    })
+}
+
+/// Releases the bytes of a [`slidevid_convert_to_mp4`] call.
+///
+/// # Safety
+///
+/// `data` and `len` must be what a successful [`slidevid_convert_to_mp4`] stored, or `data` null.
+#[no_mangle]
+pub unsafe extern "C" fn slidevid_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ffi::{slidevid_last_error_message, SLIDEVID_ERROR, SLIDEVID_OK},
+        test_util::make_test_zip,
+    };
+    use std::ffi::CString;
+
+    #[test]
+    fn converts_into_a_buffer_the_caller_frees() {
+        let zip = make_test_zip(&[(64, 48, "png")]);
+        let name = CString::new("0.png").unwrap();
+        let frames = [SlidevidFrame {
+            filename: name.as_ptr(),
+            delay_ms: 100,
+        }];
+        let (mut output, mut output_len) = (ptr::null_mut(), 0);
+        unsafe {
+            let code = slidevid_convert_to_mp4(
+                zip.as_ptr(),
+                zip.len(),
+                frames.as_ptr(),
+                frames.len(),
+                ptr::null_mut(),
+                &mut output_len,
+            );
+            assert_eq!(code, SLIDEVID_ERROR);
+            let code = slidevid_convert_to_mp4(
+                zip.as_ptr(),
+                zip.len(),
+                frames.as_ptr(),
+                frames.len(),
+                &mut output,
+                &mut output_len,
+            );
+            assert_eq!(code, SLIDEVID_OK);
+            assert!(slidevid_last_error_message().is_null());
+            assert_eq!(
+                &std::slice::from_raw_parts(output, output_len)[4..8],
+                b"ftyp"
+            );
+            slidevid_free(output, output_len);
+        }
+    }
+}